@@ -1,22 +1,26 @@
-use std::fs::read_to_string;
+use nom::{character::complete::i128 as parse_i128, IResult};
 
-fn levels(content: &str) -> Vec<u32> {
-    let mut v = Vec::new();
-    for line in content.split("\n") {
-        if line.is_empty() {
-            continue;
-        }
+mod report;
 
-        if let Ok(num) = line.parse() {
-            v.push(num);
-        }
+use report::SonarReport;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Depth(i128);
+
+impl utils::Parsable for Depth {
+    fn parse(input: &str) -> IResult<&str, Depth> {
+        let (input, depth) = parse_i128(input)?;
+
+        Ok((input, Depth(depth)))
     }
+}
 
-    v
+fn levels(depths: &[Depth]) -> Vec<i128> {
+    depths.iter().map(|d| d.0).collect()
 }
 
-fn increases(l: &[u32]) -> u32 {
-    if l.len() == 0 {
+fn increases(l: &[i128]) -> u32 {
+    if l.is_empty() {
         return 0;
     }
 
@@ -34,49 +38,92 @@ fn increases(l: &[u32]) -> u32 {
     inc
 }
 
-fn windows(l: &[u32]) -> Vec<(u32, u32, u32)> {
+fn windows(l: &[i128]) -> Vec<(i128, i128, i128)> {
     let mut res = Vec::new();
 
-    for i in 0..l.len() - 2 {
+    for i in 0..l.len().saturating_sub(2) {
         res.push((l[i], l[i + 1], l[i + 2]));
     }
 
     res
 }
 
+// A window sum grows exactly when the reading entering the window beats the
+// one leaving it, so this never has to add readings up.
+fn windowed_increases(l: &[i128]) -> u32 {
+    l.windows(4).filter(|w| w[3] > w[0]).count() as u32
+}
+
 fn main() {
-    let content = read_to_string("input").unwrap();
-    let l = levels(&content);
+    let depths = match utils::try_input_vec::<Depth>() {
+        Ok(depths) => depths,
+        Err(errors) => {
+            for e in errors {
+                eprintln!("{}", e);
+            }
+            std::process::exit(1);
+        }
+    };
 
-    println!("increases: {}", increases(&l));
+    let l = levels(&depths);
 
-    let win = windows(&l);
-    let blended = win.iter().map(|(a, b, c)| a + b + c).collect::<Vec<_>>();
+    match std::env::args().nth(1).as_deref() {
+        Some("--report") => {
+            print!("{}", SonarReport::new(&l));
+            return;
+        }
+        Some("--json") => {
+            println!("{}", SonarReport::new(&l).json());
+            return;
+        }
+        _ => (),
+    }
+
+    println!("increases: {}", increases(&l));
 
-    println!("windowed increases: {}", increases(&blended));
+    println!("windowed increases: {}", windowed_increases(&l));
 }
 
 #[test]
 fn test_input_model() {
-    let content = read_to_string("test-input").unwrap();
-    let l = levels(&content);
+    let l = levels(&utils::test_input_vec::<Depth>());
 
     assert_eq!(&l, &[199, 200, 208, 210, 200, 207, 240, 269, 260, 263]);
 }
 
 #[test]
 fn test_answer() {
-    let content = read_to_string("test-input").unwrap();
-    let l = levels(&content);
+    let l = levels(&utils::test_input_vec::<Depth>());
     assert_eq!(increases(&l), 7);
 }
 
 #[test]
 fn test_second() {
-    let content = read_to_string("test-input").unwrap();
-    let l = levels(&content);
+    let l = levels(&utils::test_input_vec::<Depth>());
     let win = windows(&l);
 
     let x = win.iter().map(|(a, b, c)| a + b + c).collect::<Vec<_>>();
     assert_eq!(increases(&x), 5);
+    assert_eq!(windowed_increases(&l), 5);
+}
+
+#[test]
+fn test_depth_range() {
+    let depths = utils::parse_lines::<Depth>("-5\n18446744073709551615\n0\n").unwrap();
+
+    assert_eq!(levels(&depths), &[-5, u64::MAX as i128, 0]);
+    assert_eq!(increases(&levels(&depths)), 1);
+}
+
+#[test]
+fn test_malformed() {
+    let errors = utils::parse_lines::<Depth>("1\n2x\n\n3\n").unwrap_err();
+
+    assert_eq!(
+        errors,
+        vec![
+            utils::LineError { line: 2, content: "2x".to_string() },
+            utils::LineError { line: 3, content: "".to_string() },
+        ]
+    );
 }
@@ -0,0 +1,245 @@
+use crate::{increases, windowed_increases, windows};
+
+use std::fmt;
+
+const HISTOGRAM_BUCKETS: u128 = 10;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SonarReport {
+    pub count: usize,
+    pub min: i128,
+    pub max: i128,
+    pub increases: u32,
+    pub windowed_increases: u32,
+    pub longest_increasing: usize,
+    pub longest_decreasing: usize,
+    pub max_drop: u128,
+    pub moving_averages: Vec<f64>,
+    pub percentiles: Vec<(u32, i128)>,
+    pub histogram: Vec<Bucket>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bucket {
+    pub low: i128,
+    pub high: i128,
+    pub count: usize,
+}
+
+impl SonarReport {
+    pub fn new(l: &[i128]) -> SonarReport {
+        let mut min = i128::MAX;
+        let mut max = i128::MIN;
+        let mut longest_increasing = 0;
+        let mut longest_decreasing = 0;
+        let mut max_drop = 0;
+
+        let mut inc_run = 0;
+        let mut dec_run = 0;
+        let mut prev: Option<i128> = None;
+
+        for cur in l.iter().copied() {
+            min = min.min(cur);
+            max = max.max(cur);
+
+            inc_run = match prev {
+                Some(p) if cur > p => inc_run + 1,
+                _ => 1,
+            };
+
+            dec_run = match prev {
+                Some(p) if cur < p => dec_run + 1,
+                _ => 1,
+            };
+
+            if let Some(p) = prev.filter(|p| *p > cur) {
+                max_drop = max_drop.max(p.abs_diff(cur));
+            }
+
+            longest_increasing = longest_increasing.max(inc_run);
+            longest_decreasing = longest_decreasing.max(dec_run);
+            prev = Some(cur);
+        }
+
+        if l.is_empty() {
+            min = 0;
+            max = 0;
+        }
+
+        // summed as floats, three readings near the ends of i128 overflow it
+        let moving_averages = windows(l).iter().map(|(a, b, c)| (*a as f64 + *b as f64 + *c as f64) / 3.0).collect();
+
+        SonarReport {
+            count: l.len(),
+            min,
+            max,
+            increases: increases(l),
+            windowed_increases: windowed_increases(l),
+            longest_increasing,
+            longest_decreasing,
+            max_drop,
+            moving_averages,
+            percentiles: percentiles(l, &[50, 90, 95, 99]),
+            histogram: histogram(l, min, max),
+        }
+    }
+
+    pub fn json(&self) -> String {
+        let averages = self
+            .moving_averages
+            .iter()
+            .map(|a| format!("{:.3}", a))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let percentiles = self
+            .percentiles
+            .iter()
+            .map(|(p, v)| format!("\"p{}\":{}", p, v))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let histogram = self
+            .histogram
+            .iter()
+            .map(|b| format!("{{\"low\":{},\"high\":{},\"count\":{}}}", b.low, b.high, b.count))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"count\":{},\"min\":{},\"max\":{},\"increases\":{},\"windowed_increases\":{},\
+             \"longest_increasing\":{},\"longest_decreasing\":{},\"max_drop\":{},\
+             \"moving_averages\":[{}],\"percentiles\":{{{}}},\"histogram\":[{}]}}",
+            self.count,
+            self.min,
+            self.max,
+            self.increases,
+            self.windowed_increases,
+            self.longest_increasing,
+            self.longest_decreasing,
+            self.max_drop,
+            averages,
+            percentiles,
+            histogram,
+        )
+    }
+}
+
+// nearest-rank percentiles over a sorted copy of the series
+fn percentiles(l: &[i128], ranks: &[u32]) -> Vec<(u32, i128)> {
+    if l.is_empty() {
+        return Vec::new();
+    }
+
+    let mut sorted = l.to_vec();
+    sorted.sort_unstable();
+
+    ranks
+        .iter()
+        .map(|p| {
+            let rank = (*p as usize * sorted.len()).div_ceil(100).max(1);
+            (*p, sorted[rank - 1])
+        })
+        .collect()
+}
+
+fn histogram(l: &[i128], min: i128, max: i128) -> Vec<Bucket> {
+    if l.is_empty() {
+        return Vec::new();
+    }
+
+    // one less than the number of values in the range, which always fits
+    let spread = max.abs_diff(min);
+    let width = spread / HISTOGRAM_BUCKETS + 1;
+    let buckets = spread / width + 1;
+
+    let mut res = (0..buckets)
+        .map(|i| {
+            let low = min.saturating_add_unsigned(i * width);
+            let high = low.saturating_add_unsigned(width - 1).min(max);
+            Bucket { low, high, count: 0 }
+        })
+        .collect::<Vec<_>>();
+
+    for cur in l {
+        res[(cur.abs_diff(min) / width) as usize].count += 1;
+    }
+
+    res
+}
+
+impl fmt::Display for SonarReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "{:<20} {:>10}", "readings", self.count)?;
+        writeln!(fmt, "{:<20} {:>10}", "min", self.min)?;
+        writeln!(fmt, "{:<20} {:>10}", "max", self.max)?;
+        writeln!(fmt, "{:<20} {:>10}", "increases", self.increases)?;
+        writeln!(fmt, "{:<20} {:>10}", "windowed increases", self.windowed_increases)?;
+        writeln!(fmt, "{:<20} {:>10}", "longest increasing", self.longest_increasing)?;
+        writeln!(fmt, "{:<20} {:>10}", "longest decreasing", self.longest_decreasing)?;
+        writeln!(fmt, "{:<20} {:>10}", "max drop", self.max_drop)?;
+
+        let lo = self.moving_averages.iter().copied().fold(f64::NAN, f64::min);
+        let hi = self.moving_averages.iter().copied().fold(f64::NAN, f64::max);
+        writeln!(fmt, "{:<20} {:>10.3}", "min moving average", lo)?;
+        writeln!(fmt, "{:<20} {:>10.3}", "max moving average", hi)?;
+
+        for (p, v) in &self.percentiles {
+            writeln!(fmt, "{:<20} {:>10}", format!("p{}", p), v)?;
+        }
+
+        writeln!(fmt)?;
+
+        let peak = self.histogram.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+        for b in &self.histogram {
+            let bar = "#".repeat(b.count * 40 / peak);
+            writeln!(fmt, "{:>6}..{:<6} {:>6} {}", b.low, b.high, b.count, bar)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_report() {
+    let report = SonarReport::new(&[199, 200, 208, 210, 200, 207, 240, 269, 260, 263]);
+
+    assert_eq!(report.count, 10);
+    assert_eq!(report.min, 199);
+    assert_eq!(report.max, 269);
+    assert_eq!(report.increases, 7);
+    assert_eq!(report.windowed_increases, 5);
+    assert_eq!(report.longest_increasing, 4);
+    assert_eq!(report.longest_decreasing, 2);
+    assert_eq!(report.max_drop, 10);
+    assert_eq!(report.moving_averages.len(), 8);
+    assert_eq!(report.moving_averages[0], 607.0 / 3.0);
+    assert_eq!(report.percentiles, vec![(50, 208), (90, 263), (95, 269), (99, 269)]);
+    assert_eq!(report.histogram.iter().map(|b| b.count).sum::<usize>(), 10);
+    assert_eq!(report.histogram[0], Bucket { low: 199, high: 206, count: 3 });
+}
+
+#[test]
+fn test_report_json() {
+    let report = SonarReport::new(&[3, 1, 2]);
+
+    assert_eq!(
+        report.json(),
+        "{\"count\":3,\"min\":1,\"max\":3,\"increases\":1,\"windowed_increases\":0,\
+         \"longest_increasing\":2,\"longest_decreasing\":2,\"max_drop\":2,\
+         \"moving_averages\":[2.000],\"percentiles\":{\"p50\":2,\"p90\":3,\"p95\":3,\"p99\":3},\
+         \"histogram\":[{\"low\":1,\"high\":1,\"count\":1},{\"low\":2,\"high\":2,\"count\":1},\
+         {\"low\":3,\"high\":3,\"count\":1}]}"
+    );
+}
+
+#[test]
+fn test_report_extremes() {
+    let report = SonarReport::new(&[i128::MAX, i128::MIN, i128::MAX, i128::MIN]);
+
+    assert_eq!(report.max_drop, u128::MAX);
+    assert_eq!(report.windowed_increases, 0);
+    assert_eq!(report.histogram.len(), 10);
+    assert_eq!(report.histogram[0].count, 2);
+    assert_eq!(report.histogram[9], Bucket { low: i128::MIN.saturating_add_unsigned(9 * (u128::MAX / 10 + 1)), high: i128::MAX, count: 2 });
+}
@@ -1,69 +1,243 @@
 use nom::{bytes::complete::tag, branch::alt, combinator::map_res, character::complete::digit1, IResult};
 
+mod plan;
+mod sub;
+mod trajectory;
+
+use sub::{Aim, Plain, SubError, Submarine, Surfacing};
+use trajectory::Trajectory;
+
 fn main() {
-    let input = utils::input_vec::<Motion>();
+    let mut surfacing = Surfacing::default();
+    let mut export = None;
+    let mut target = None;
+    let mut bound = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--surfacing", Some(policy)) => {
+                surfacing = policy.parse().unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                })
+            }
+            ("--plan", Some(t)) => target = Some(parse_target(&t)),
+            ("--max", Some(m)) => bound = Some(m.parse::<u32>().unwrap_or_else(|_| usage())),
+            ("--trajectory", Some(format)) if format == "csv" || format == "svg" => export = Some(format),
+            _ => usage(),
+        }
+    }
 
+    if let Some((hor, depth)) = target {
+        let plan = match bound {
+            Some(max) => plan::plan_bounded(hor, depth, max),
+            None => plan::plan_minimal(hor, depth),
+        };
 
-    let (depth, hor) = resolve_position(&input);
+        match plan {
+            Ok(motions) => print!("{}", plan::serialize(&motions)),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
 
-    println!("calculation: {}", depth * hor);
+    let content = std::fs::read_to_string("input").expect("reading input");
+    let input = match parse_program(&content) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-    let (depth, hor) = resolve_aim_position(&input);
+    if let Some(format) = export {
+        match Trajectory::record(Submarine::new(Aim).surfacing(surfacing), &input) {
+            Ok(traj) if format == "csv" => print!("{}", traj.csv()),
+            Ok(traj) => print!("{}", traj.svg()),
+            Err(e) => eprintln!("trajectory: {}", e),
+        }
 
-    println!("part2 calculation: {}", depth * hor);
-}
+        return;
+    }
 
-fn resolve_aim_position(motions: &[Motion]) -> (u32, u32) {
-    let mut depth = 0;
-    let mut hor = 0;
-    let mut aim: i32 = 0;
+    match resolve_position(&input, surfacing).and_then(answer) {
+        Ok(answer) => println!("calculation: {}", answer),
+        Err(e) => eprintln!("calculation: {}", e),
+    }
 
-    for m in motions {
-        match m {
-            Motion::Forward(m) => {
-                hor += m;
-                depth = (depth as i32 + (aim * *m as i32)) as u32
-            }
-            Motion::Up(m) => aim -= *m as i32,
-            Motion::Down(m) => aim += *m as i32,
-        };
+    match resolve_aim_position(&input, surfacing).and_then(answer) {
+        Ok(answer) => println!("part2 calculation: {}", answer),
+        Err(e) => eprintln!("part2 calculation: {}", e),
     }
 
-    (depth, hor)
+    if let Ok(traj) = Trajectory::record(Submarine::new(Aim).surfacing(surfacing), &input) {
+        if let Some((step, depth)) = traj.max_depth() {
+            println!("part2 max depth: {} at step {}", depth, step);
+        }
+    }
 }
 
-fn resolve_position(motions: &[Motion]) -> (u32, u32) {
-    let mut depth = 0;
-    let mut hor = 0;
+fn usage() -> ! {
+    eprintln!("usage: day-2 [--surfacing clamp|error|allow] [--trajectory csv|svg] [--plan hor,depth [--max N]]");
+    std::process::exit(1);
+}
 
-    for m in motions {
-        match m {
-            Motion::Forward(m) => hor += m,
-            Motion::Up(m) => depth -= m,
-            Motion::Down(m) => depth += m,
-        };
+fn parse_target(t: &str) -> (i64, i64) {
+    let (hor, depth) = t.split_once(',').unwrap_or_else(|| usage());
+
+    match (hor.parse(), depth.parse()) {
+        (Ok(hor), Ok(depth)) => (hor, depth),
+        _ => usage(),
     }
+}
 
-    (depth, hor)
+fn answer((depth, hor): (i64, i64)) -> Result<i64, SubError> {
+    depth.checked_mul(hor).ok_or(SubError::Answer)
 }
 
-#[derive(Debug, PartialEq, Eq)]
+fn resolve_aim_position(motions: &[Motion], surfacing: Surfacing) -> Result<(i64, i64), SubError> {
+    let mut sub = Submarine::new(Aim).surfacing(surfacing);
+    sub.run(motions)?;
+
+    Ok((sub.state().depth, sub.state().hor))
+}
+
+fn resolve_position(motions: &[Motion], surfacing: Surfacing) -> Result<(i64, i64), SubError> {
+    let mut sub = Submarine::new(Plain).surfacing(surfacing);
+    sub.run(motions)?;
+
+    Ok((sub.state().depth, sub.state().hor))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Motion {
     Up(u32),
     Down(u32),
     Forward(u32),
+    Back(u32),
+    ResetAim,
+    Repeat(u32, Vec<Motion>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProgramError {
+    Unknown { line: usize, command: String },
+    Unclosed { line: usize },
+    Unopened { line: usize },
+}
+
+impl std::fmt::Display for Motion {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Motion::Up(m) => write!(fmt, "up {}", m),
+            Motion::Down(m) => write!(fmt, "down {}", m),
+            Motion::Forward(m) => write!(fmt, "forward {}", m),
+            Motion::Back(m) => write!(fmt, "back {}", m),
+            Motion::ResetAim => write!(fmt, "reset aim"),
+            Motion::Repeat(n, body) => {
+                writeln!(fmt, "repeat {} {{", n)?;
+                for m in body {
+                    for line in m.to_string().lines() {
+                        writeln!(fmt, "  {}", line)?;
+                    }
+                }
+                write!(fmt, "}}")
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ProgramError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ProgramError::Unknown { line, command } => {
+                write!(fmt, "line {}: unknown command {:?}", line, command)
+            }
+            ProgramError::Unclosed { line } => write!(fmt, "line {}: repeat block never closed", line),
+            ProgramError::Unopened { line } => write!(fmt, "line {}: '}}' without repeat block", line),
+        }
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+fn magnitude(input: &str) -> IResult<&str, u32> {
+    map_res(digit1, |s: &str| s.parse::<u32>())(input)
+}
+
+fn repeat_header(input: &str) -> IResult<&str, u32> {
+    let (input, _) = tag("repeat ")(input)?;
+    let (input, n) = magnitude(input)?;
+    let (input, _) = tag(" {")(input)?;
+
+    Ok((input, n))
+}
+
+pub fn parse_program(content: &str) -> Result<Vec<Motion>, ProgramError> {
+    // each open repeat block keeps its header line, count and body so far
+    let mut blocks: Vec<(usize, u32, Vec<Motion>)> = Vec::new();
+    let mut motions = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line == "}" {
+            let (_, n, body) = blocks.pop().ok_or(ProgramError::Unopened { line: line_no })?;
+            let current = blocks.last_mut().map_or(&mut motions, |b| &mut b.2);
+            current.push(Motion::Repeat(n, body));
+            continue;
+        }
+
+        if let Ok(("", n)) = repeat_header(line) {
+            blocks.push((line_no, n, Vec::new()));
+            continue;
+        }
+
+        let motion = match <Motion as utils::Parsable>::parse(line) {
+            Ok(("", motion)) => motion,
+            _ => {
+                return Err(ProgramError::Unknown {
+                    line: line_no,
+                    command: line.to_string(),
+                })
+            }
+        };
+
+        let current = blocks.last_mut().map_or(&mut motions, |b| &mut b.2);
+        current.push(motion);
+    }
+
+    if let Some((line, _, _)) = blocks.first() {
+        return Err(ProgramError::Unclosed { line: *line });
+    }
+
+    Ok(motions)
 }
 
 impl utils::Parsable for Motion {
     fn parse(input: &str) -> IResult<&str, Motion> {
-        let (input, dir) = alt((tag("forward"), tag("down"), tag("up")))(input)?;
+        if let Ok((input, _)) = tag::<_, _, ()>("reset aim")(input) {
+            return Ok((input, Motion::ResetAim));
+        }
+
+        let (input, dir) = alt((tag("forward"), tag("down"), tag("up"), tag("back")))(input)?;
         let (input, _) = tag(" ")(input)?;
-        let (input, mag) = map_res(digit1, |s: &str| s.parse::<u32>())(input)?;
+        let (input, mag) = magnitude(input)?;
 
         Ok((input, match dir {
             "forward" => Motion::Forward(mag),
             "down" => Motion::Down(mag),
             "up" => Motion::Up(mag),
+            "back" => Motion::Back(mag),
             _ => unreachable!(),
         }))
     }
@@ -83,14 +257,57 @@ fn test_model() {
 
 #[test]
 fn test_result() {
-    let (depth, hor) = resolve_position(&utils::test_input_vec::<Motion>());
+    let (depth, hor) = resolve_position(&utils::test_input_vec::<Motion>(), Surfacing::Error).unwrap();
 
     assert_eq!(depth * hor, 150);
+    assert_eq!(answer((depth, hor)), Ok(150));
+}
+
+#[test]
+fn test_answer_overflow() {
+    let motions = parse_program("repeat 4 {\n  forward 4294967295\n  down 4294967295\n}\n").unwrap();
+    let position = resolve_position(&motions, Surfacing::Error).unwrap();
+
+    assert_eq!(position, (4 * 4294967295, 4 * 4294967295));
+    assert_eq!(answer(position), Err(SubError::Answer));
 }
 
 #[test]
 fn test_2result() {
-    let (depth, hor) = resolve_aim_position(&utils::test_input_vec::<Motion>());
+    let (depth, hor) = resolve_aim_position(&utils::test_input_vec::<Motion>(), Surfacing::Error).unwrap();
 
     assert_eq!(depth * hor, 900);
 }
+
+#[test]
+fn test_program() {
+    let program = "# dive\ndown 2\nrepeat 2 {\n  forward 1 # step\n  repeat 3 {\n    down 1\n  }\n}\nreset aim\nback 1\n";
+
+    assert_eq!(
+        parse_program(program),
+        Ok(vec![
+            Motion::Down(2),
+            Motion::Repeat(2, vec![Motion::Forward(1), Motion::Repeat(3, vec![Motion::Down(1)])]),
+            Motion::ResetAim,
+            Motion::Back(1),
+        ])
+    );
+}
+
+#[test]
+fn test_program_errors() {
+    assert_eq!(
+        parse_program("forward 1\nsideways 3\n"),
+        Err(ProgramError::Unknown { line: 2, command: "sideways 3".to_string() })
+    );
+    assert_eq!(parse_program("down 1\nrepeat 2 {\nup 1\n"), Err(ProgramError::Unclosed { line: 2 }));
+    assert_eq!(parse_program("down 1\n}\n"), Err(ProgramError::Unopened { line: 2 }));
+}
+
+#[test]
+fn test_display() {
+    let program = "down 2\nrepeat 2 {\n  forward 1\n  repeat 3 {\n    up 1\n  }\n}\nreset aim\nback 1\n";
+    let motions = parse_program(program).unwrap();
+
+    assert_eq!(plan::serialize(&motions), program);
+}
@@ -0,0 +1,324 @@
+use crate::Motion;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PlanError {
+    Unreachable,
+    ZeroBound,
+}
+
+impl std::fmt::Display for PlanError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PlanError::Unreachable => write!(fmt, "target cannot be reached moving forward"),
+            PlanError::ZeroBound => write!(fmt, "magnitude bound must be positive"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+fn check(hor: i64, depth: i64) -> Result<(), PlanError> {
+    if hor < 0 || (hor == 0 && depth != 0) {
+        return Err(PlanError::Unreachable);
+    }
+
+    Ok(())
+}
+
+fn aim_change(delta: u64, max: u64, out: &mut Vec<Motion>, down: bool) {
+    chunk(delta, max, out, |m| if down { Motion::Down(m) } else { Motion::Up(m) });
+}
+
+fn chunk(mut total: u64, max: u64, out: &mut Vec<Motion>, motion: impl Fn(u32) -> Motion) {
+    while total > 0 {
+        let m = total.min(max);
+        out.push(motion(m as u32));
+        total -= m;
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut res = 1 % m;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            res = mul_mod(res, base, m);
+        }
+
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+
+    res
+}
+
+// Miller-Rabin, these bases are enough for every u64
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+    if n < 2 {
+        return false;
+    }
+
+    if let Some(p) = BASES.iter().find(|p| n.is_multiple_of(**p)) {
+        return n == *p;
+    }
+
+    let (mut odd, mut twos) = (n - 1, 0);
+    while odd.is_multiple_of(2) {
+        odd /= 2;
+        twos += 1;
+    }
+
+    BASES.iter().all(|a| {
+        let mut x = pow_mod(*a, odd, n);
+
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+
+        for _ in 1..twos {
+            x = mul_mod(x, x, n);
+
+            if x == n - 1 {
+                return true;
+            }
+        }
+
+        false
+    })
+}
+
+// Pollard's rho, some factor of a composite n other than 1 and n
+fn split(n: u64) -> u64 {
+    if n.is_multiple_of(2) {
+        return 2;
+    }
+
+    for c in 1.. {
+        let f = |x: u64| ((mul_mod(x, x, n) as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+
+        if d != n {
+            return d;
+        }
+    }
+
+    unreachable!()
+}
+
+fn factor(n: u64, primes: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+
+    if is_prime(n) {
+        primes.push(n);
+        return;
+    }
+
+    let d = split(n);
+    factor(d, primes);
+    factor(n / d, primes);
+}
+
+// Largest divisor of n strictly below bound, found among all divisors built
+// from the prime factors. A u64 has at most about 100k divisors, where trial
+// division could take billions of steps.
+fn divisor_below(n: u64, bound: u64) -> u64 {
+    let mut primes = Vec::new();
+    factor(n, &mut primes);
+    primes.sort_unstable();
+
+    let mut divisors = vec![1u64];
+
+    for run in primes.chunk_by(|a, b| a == b) {
+        let mut next = Vec::with_capacity(divisors.len() * (run.len() + 1));
+
+        for d in &divisors {
+            let mut m = *d;
+            next.push(m);
+
+            for p in run {
+                m *= p;
+                next.push(m);
+            }
+        }
+
+        divisors = next;
+    }
+
+    divisors.into_iter().filter(|d| *d < bound).max().unwrap_or(1)
+}
+
+// Shortest aim-mode program reaching the target: at most three commands.
+// Falls back to plan_bounded when a magnitude would not fit in a command.
+pub fn plan_minimal(hor: i64, depth: i64) -> Result<Vec<Motion>, PlanError> {
+    check(hor, depth)?;
+
+    let h = hor as u64;
+    let d = depth.unsigned_abs();
+    let fits = |m: u64| m <= u32::MAX as u64;
+
+    if !fits(h) {
+        return plan_bounded(hor, depth, u32::MAX);
+    }
+
+    let plan = if hor == 0 {
+        vec![]
+    } else if depth == 0 {
+        vec![Motion::Forward(h as u32)]
+    } else if d.is_multiple_of(h) {
+        let aim = d / h;
+
+        if !fits(aim) {
+            return plan_bounded(hor, depth, u32::MAX);
+        }
+
+        let turn = if depth > 0 { Motion::Down(aim as u32) } else { Motion::Up(aim as u32) };
+        vec![turn, Motion::Forward(h as u32)]
+    } else {
+        // cruise at zero aim first, then cover the whole depth in the last stretch
+        let last = divisor_below(d, h);
+        let aim = d / last;
+
+        if !fits(aim) {
+            return plan_bounded(hor, depth, u32::MAX);
+        }
+
+        let turn = if depth > 0 { Motion::Down(aim as u32) } else { Motion::Up(aim as u32) };
+        vec![Motion::Forward((h - last) as u32), turn, Motion::Forward(last as u32)]
+    };
+
+    Ok(plan)
+}
+
+// Aim-mode program where no command moves more than max. Sets the aim to
+// depth / hor, covers most of the distance, then nudges the aim by one for
+// the remainder.
+pub fn plan_bounded(hor: i64, depth: i64, max: u32) -> Result<Vec<Motion>, PlanError> {
+    check(hor, depth)?;
+
+    if max == 0 {
+        return if hor == 0 { Ok(vec![]) } else { Err(PlanError::ZeroBound) };
+    }
+
+    let mut out = Vec::new();
+
+    if hor == 0 {
+        return Ok(out);
+    }
+
+    let max = max as u64;
+    let h = hor as u64;
+    let d = depth.unsigned_abs();
+    let down = depth > 0;
+
+    let aim = d / h;
+    let rem = d % h;
+
+    aim_change(aim, max, &mut out, down);
+    chunk(h - rem, max, &mut out, Motion::Forward);
+
+    if rem > 0 {
+        aim_change(1, max, &mut out, down);
+        chunk(rem, max, &mut out, Motion::Forward);
+    }
+
+    Ok(out)
+}
+
+pub fn serialize(motions: &[Motion]) -> String {
+    let mut out = String::new();
+
+    for m in motions {
+        out.push_str(&m.to_string());
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+fn reach(motions: &[Motion]) -> (i64, i64) {
+    use crate::sub::{Aim, Submarine, Surfacing};
+
+    let program = crate::parse_program(&serialize(motions)).unwrap();
+    let mut sub = Submarine::new(Aim).surfacing(Surfacing::AllowNegative);
+    sub.run(&program).unwrap();
+
+    (sub.state().hor, sub.state().depth)
+}
+
+#[test]
+fn test_minimal() {
+    assert_eq!(plan_minimal(0, 0), Ok(vec![]));
+    assert_eq!(plan_minimal(15, 0), Ok(vec![Motion::Forward(15)]));
+    assert_eq!(plan_minimal(15, 60), Ok(vec![Motion::Down(4), Motion::Forward(15)]));
+    assert_eq!(
+        plan_minimal(15, 61),
+        Ok(vec![Motion::Forward(14), Motion::Down(61), Motion::Forward(1)])
+    );
+    assert_eq!(
+        plan_minimal(10, -42),
+        Ok(vec![Motion::Forward(3), Motion::Up(6), Motion::Forward(7)])
+    );
+    assert_eq!(plan_minimal(0, 3), Err(PlanError::Unreachable));
+    assert_eq!(plan_minimal(-1, 0), Err(PlanError::Unreachable));
+}
+
+#[test]
+fn test_bounded() {
+    let plan = plan_bounded(15, 61, 5).unwrap();
+
+    assert!(plan.iter().all(|m| !matches!(m, Motion::Forward(n) | Motion::Down(n) | Motion::Up(n) if *n > 5)));
+    assert_eq!(reach(&plan), (15, 61));
+    assert_eq!(plan_bounded(3, 0, 0), Err(PlanError::ZeroBound));
+}
+
+#[test]
+fn test_divisor_below() {
+    for n in 1..300 {
+        for bound in 1..40 {
+            let naive = (1..bound.max(2)).filter(|d| n % d == 0).max().unwrap();
+            assert_eq!(divisor_below(n, bound), naive, "{} below {}", n, bound);
+        }
+    }
+
+    // two primes just under 2^31, which trial division would count up to
+    let (p, q) = (2147483629, 2147483647);
+    assert_eq!(divisor_below(p * q, u32::MAX as u64), q);
+    assert_eq!(divisor_below(p * q, q), p);
+    assert_eq!(divisor_below(1 << 62, 1 << 40), 1 << 39);
+
+    let plan = plan_minimal(u32::MAX as i64, (p * q) as i64).unwrap();
+    assert_eq!(plan, vec![Motion::Forward(u32::MAX - q as u32), Motion::Down(p as u32), Motion::Forward(q as u32)]);
+}
+
+#[test]
+fn test_round_trip() {
+    for hor in 1..30 {
+        for depth in -100..100 {
+            assert_eq!(reach(&plan_minimal(hor, depth).unwrap()), (hor, depth));
+            assert_eq!(reach(&plan_bounded(hor, depth, 7).unwrap()), (hor, depth));
+        }
+    }
+
+    for far in [(u32::MAX as i64 * 3, 1 << 40), (1, 1 << 40), (7, -(1 << 40) - 1)] {
+        assert_eq!(reach(&plan_minimal(far.0, far.1).unwrap()), far);
+    }
+}
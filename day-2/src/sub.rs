@@ -0,0 +1,251 @@
+use crate::trajectory::Trace;
+use crate::Motion;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct State {
+    pub depth: i64,
+    pub hor: i64,
+    pub aim: i64,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Surfacing {
+    Clamp,
+    #[default]
+    Error,
+    AllowNegative,
+}
+
+impl std::str::FromStr for Surfacing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Surfacing, String> {
+        match s {
+            "clamp" => Ok(Surfacing::Clamp),
+            "error" => Ok(Surfacing::Error),
+            "allow" => Ok(Surfacing::AllowNegative),
+            _ => Err(format!("unknown surfacing policy {:?}", s)),
+        }
+    }
+}
+
+// index counts every executed command, so commands inside a repeat block
+// get a new index on each pass
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SubError {
+    Overflow { index: usize },
+    Surfaced { index: usize },
+    // depth times horizontal position, the puzzle answer, does not fit
+    Answer,
+}
+
+impl std::fmt::Display for SubError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SubError::Overflow { index } => write!(fmt, "command {}: arithmetic overflow", index),
+            SubError::Surfaced { index } => write!(fmt, "command {}: submarine above surface", index),
+            SubError::Answer => write!(fmt, "answer: arithmetic overflow"),
+        }
+    }
+}
+
+impl std::error::Error for SubError {}
+
+// each operation returns None when the state would overflow
+pub trait Semantics {
+    fn forward(&self, state: &mut State, mag: i64) -> Option<()>;
+    fn back(&self, state: &mut State, mag: i64) -> Option<()>;
+    fn up(&self, state: &mut State, mag: i64) -> Option<()>;
+    fn down(&self, state: &mut State, mag: i64) -> Option<()>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Plain;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Aim;
+
+impl Semantics for Plain {
+    fn forward(&self, state: &mut State, mag: i64) -> Option<()> {
+        state.hor = state.hor.checked_add(mag)?;
+        Some(())
+    }
+
+    fn back(&self, state: &mut State, mag: i64) -> Option<()> {
+        state.hor = state.hor.checked_sub(mag)?;
+        Some(())
+    }
+
+    fn up(&self, state: &mut State, mag: i64) -> Option<()> {
+        state.depth = state.depth.checked_sub(mag)?;
+        Some(())
+    }
+
+    fn down(&self, state: &mut State, mag: i64) -> Option<()> {
+        state.depth = state.depth.checked_add(mag)?;
+        Some(())
+    }
+}
+
+impl Semantics for Aim {
+    fn forward(&self, state: &mut State, mag: i64) -> Option<()> {
+        let hor = state.hor.checked_add(mag)?;
+        let depth = state.depth.checked_add(state.aim.checked_mul(mag)?)?;
+
+        state.hor = hor;
+        state.depth = depth;
+        Some(())
+    }
+
+    fn back(&self, state: &mut State, mag: i64) -> Option<()> {
+        let hor = state.hor.checked_sub(mag)?;
+        let depth = state.depth.checked_sub(state.aim.checked_mul(mag)?)?;
+
+        state.hor = hor;
+        state.depth = depth;
+        Some(())
+    }
+
+    fn up(&self, state: &mut State, mag: i64) -> Option<()> {
+        state.aim = state.aim.checked_sub(mag)?;
+        Some(())
+    }
+
+    fn down(&self, state: &mut State, mag: i64) -> Option<()> {
+        state.aim = state.aim.checked_add(mag)?;
+        Some(())
+    }
+}
+
+pub struct Submarine<S: Semantics> {
+    state: State,
+    semantics: S,
+    surfacing: Surfacing,
+    executed: usize,
+}
+
+impl<S: Semantics> Submarine<S> {
+    pub fn new(semantics: S) -> Submarine<S> {
+        Submarine {
+            state: State::default(),
+            semantics,
+            surfacing: Surfacing::default(),
+            executed: 0,
+        }
+    }
+
+    pub fn surfacing(mut self, surfacing: Surfacing) -> Submarine<S> {
+        self.surfacing = surfacing;
+        self
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn step(&mut self, motion: &Motion) -> Result<(), SubError> {
+        if let Motion::Repeat(n, body) = motion {
+            for _ in 0..*n {
+                self.run(body)?;
+            }
+
+            return Ok(());
+        }
+
+        let index = self.executed;
+        self.executed += 1;
+
+        let state = &mut self.state;
+        let applied = match motion {
+            Motion::Forward(m) => self.semantics.forward(state, *m as i64),
+            Motion::Back(m) => self.semantics.back(state, *m as i64),
+            Motion::Up(m) => self.semantics.up(state, *m as i64),
+            Motion::Down(m) => self.semantics.down(state, *m as i64),
+            Motion::ResetAim => {
+                state.aim = 0;
+                Some(())
+            }
+            Motion::Repeat(_, _) => unreachable!(),
+        };
+
+        applied.ok_or(SubError::Overflow { index })?;
+
+        if self.state.depth < 0 {
+            match self.surfacing {
+                Surfacing::Clamp => self.state.depth = 0,
+                Surfacing::Error => return Err(SubError::Surfaced { index }),
+                Surfacing::AllowNegative => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn trace(self, motions: &[Motion]) -> Trace<'_, S> {
+        Trace::new(self, motions)
+    }
+
+    pub fn run(&mut self, motions: &[Motion]) -> Result<(), SubError> {
+        for m in motions {
+            self.step(m)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_repeat() {
+    let mut sub = Submarine::new(Plain);
+    sub.run(&[
+        Motion::Down(1),
+        Motion::Repeat(3, vec![Motion::Forward(2), Motion::Down(1)]),
+        Motion::Back(1),
+    ])
+    .unwrap();
+
+    assert_eq!(sub.state(), &State { depth: 4, hor: 5, aim: 0 });
+}
+
+#[test]
+fn test_aim_back() {
+    let mut sub = Submarine::new(Aim);
+    sub.run(&[
+        Motion::Down(2),
+        Motion::Forward(3),
+        Motion::ResetAim,
+        Motion::Forward(3),
+        Motion::Down(1),
+        Motion::Back(2),
+    ])
+    .unwrap();
+
+    assert_eq!(sub.state(), &State { depth: 4, hor: 4, aim: 1 });
+}
+
+#[test]
+fn test_surfacing() {
+    let motions = [Motion::Down(1), Motion::Up(3), Motion::Back(2)];
+
+    let mut sub = Submarine::new(Plain);
+    assert_eq!(sub.run(&motions), Err(SubError::Surfaced { index: 1 }));
+
+    let mut sub = Submarine::new(Plain).surfacing(Surfacing::Clamp);
+    sub.run(&motions).unwrap();
+    assert_eq!(sub.state(), &State { depth: 0, hor: -2, aim: 0 });
+
+    let mut sub = Submarine::new(Plain).surfacing(Surfacing::AllowNegative);
+    sub.run(&motions).unwrap();
+    assert_eq!(sub.state(), &State { depth: -2, hor: -2, aim: 0 });
+}
+
+#[test]
+fn test_overflow() {
+    let mut sub = Submarine::new(Aim);
+    let motions = [
+        Motion::Repeat(3, vec![Motion::Down(u32::MAX)]),
+        Motion::Repeat(1 << 20, vec![Motion::Forward(u32::MAX)]),
+    ];
+
+    assert_eq!(sub.run(&motions), Err(SubError::Overflow { index: 3 }));
+}
@@ -0,0 +1,194 @@
+use crate::sub::{Semantics, State, SubError, Submarine};
+use crate::Motion;
+
+use std::fmt::Write;
+
+const SVG_SIZE: f64 = 512.0;
+
+// each frame is a block body, the next index in it and how many more
+// passes over it are left
+pub struct Trace<'a, S: Semantics> {
+    sub: Submarine<S>,
+    stack: Vec<(&'a [Motion], usize, u32)>,
+    failed: bool,
+}
+
+impl<'a, S: Semantics> Trace<'a, S> {
+    pub fn new(sub: Submarine<S>, motions: &'a [Motion]) -> Trace<'a, S> {
+        Trace {
+            sub,
+            stack: vec![(motions, 0, 0)],
+            failed: false,
+        }
+    }
+}
+
+impl<'a, S: Semantics> Iterator for Trace<'a, S> {
+    type Item = Result<State, SubError>;
+
+    fn next(&mut self) -> Option<Result<State, SubError>> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            let frame = self.stack.last_mut()?;
+
+            if frame.1 == frame.0.len() {
+                if frame.2 > 0 {
+                    frame.2 -= 1;
+                    frame.1 = 0;
+                } else {
+                    self.stack.pop();
+                }
+
+                continue;
+            }
+
+            let motion = &frame.0[frame.1];
+            frame.1 += 1;
+
+            if let Motion::Repeat(n, body) = motion {
+                if *n > 0 {
+                    self.stack.push((body, 0, n - 1));
+                }
+
+                continue;
+            }
+
+            if let Err(e) = self.sub.step(motion) {
+                self.failed = true;
+                return Some(Err(e));
+            }
+
+            return Some(Ok(self.sub.state().clone()));
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trajectory {
+    states: Vec<State>,
+}
+
+impl Trajectory {
+    pub fn record<S: Semantics>(sub: Submarine<S>, motions: &[Motion]) -> Result<Trajectory, SubError> {
+        let states = sub.trace(motions).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Trajectory { states })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &State> {
+        self.states.iter()
+    }
+
+    // first step reaching the deepest point, with that depth
+    pub fn max_depth(&self) -> Option<(usize, i64)> {
+        let mut res: Option<(usize, i64)> = None;
+
+        for (i, s) in self.iter().enumerate() {
+            if res.is_none_or(|(_, d)| s.depth > d) {
+                res = Some((i, s.depth));
+            }
+        }
+
+        res
+    }
+
+    pub fn csv(&self) -> String {
+        let mut out = String::from("step,hor,depth,aim\n");
+
+        for (i, s) in self.iter().enumerate() {
+            writeln!(out, "{},{},{},{}", i, s.hor, s.depth, s.aim).unwrap();
+        }
+
+        out
+    }
+
+    // horizontal position runs left to right and depth top to bottom,
+    // both scaled to fit the same square viewport starting at the surface
+    pub fn svg(&self) -> String {
+        let points = std::iter::once((0, 0))
+            .chain(self.iter().map(|s| (s.hor, s.depth)))
+            .collect::<Vec<_>>();
+
+        let min_x = points.iter().map(|p| p.0).min().unwrap_or(0);
+        let max_x = points.iter().map(|p| p.0).max().unwrap_or(0);
+        let min_y = points.iter().map(|p| p.1).min().unwrap_or(0);
+        let max_y = points.iter().map(|p| p.1).max().unwrap_or(0);
+
+        let sx = SVG_SIZE / (max_x - min_x).max(1) as f64;
+        let sy = SVG_SIZE / (max_y - min_y).max(1) as f64;
+
+        let mut path = String::new();
+        for (i, (x, y)) in points.iter().enumerate() {
+            let cmd = if i == 0 { 'M' } else { 'L' };
+            let x = (x - min_x) as f64 * sx;
+            let y = (y - min_y) as f64 * sy;
+            write!(path, "{}{:.2},{:.2} ", cmd, x, y).unwrap();
+        }
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" width=\"{size}\" height=\"{size}\">\n\
+             <path d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>\n\
+             </svg>\n",
+            path.trim_end(),
+            size = SVG_SIZE,
+        )
+    }
+}
+
+#[test]
+fn test_trace() {
+    use crate::sub::Aim;
+
+    let motions = utils::test_input_vec::<Motion>();
+    let states = Submarine::new(Aim).trace(&motions).collect::<Result<Vec<_>, _>>().unwrap();
+
+    assert_eq!(states.len(), 6);
+    assert_eq!(states[0], State { hor: 5, depth: 0, aim: 0 });
+    assert_eq!(states[2], State { hor: 13, depth: 40, aim: 5 });
+    assert_eq!(states[5], State { hor: 15, depth: 60, aim: 10 });
+}
+
+#[test]
+fn test_trace_repeat() {
+    use crate::sub::Plain;
+
+    let motions = [
+        Motion::Repeat(2, vec![Motion::Forward(1), Motion::Repeat(0, vec![Motion::Down(9)])]),
+        Motion::Down(1),
+    ];
+    let traj = Trajectory::record(Submarine::new(Plain), &motions).unwrap();
+
+    assert_eq!(
+        traj.iter().map(|s| (s.hor, s.depth)).collect::<Vec<_>>(),
+        vec![(1, 0), (2, 0), (2, 1)]
+    );
+}
+
+#[test]
+fn test_trace_error() {
+    use crate::sub::Plain;
+
+    let motions = [Motion::Forward(1), Motion::Up(1), Motion::Down(1)];
+    let mut trace = Submarine::new(Plain).trace(&motions);
+
+    assert!(trace.next().unwrap().is_ok());
+    assert_eq!(trace.next(), Some(Err(SubError::Surfaced { index: 1 })));
+    assert_eq!(trace.next(), None);
+}
+
+#[test]
+fn test_exports() {
+    use crate::sub::Aim;
+
+    let traj = Trajectory::record(Submarine::new(Aim), &utils::test_input_vec::<Motion>()).unwrap();
+
+    assert_eq!(traj.max_depth(), Some((5, 60)));
+    assert_eq!(
+        traj.csv(),
+        "step,hor,depth,aim\n0,5,0,0\n1,5,0,5\n2,13,40,5\n3,13,40,2\n4,13,40,10\n5,15,60,10\n"
+    );
+    assert!(traj.svg().contains("M0.00,0.00 L170.67,0.00 L170.67,0.00 L443.73,341.33"));
+}
@@ -1,93 +1,122 @@
 use nom::{character::complete::one_of, IResult};
 
+mod report;
+mod trie;
+
+use utils::big::Big;
+use report::Report;
+use trie::Trie;
+
 fn main() {
-    let bins = utils::input_vec::<Bin>();
-    println!("power comsumption: {}", comsumption(&bins));
-    println!("life support: {}", life_support(&bins));
-}
+    let content = std::fs::read_to_string("input").expect("reading input");
+    let bins = match diagnostics(&content) {
+        Ok(bins) => bins,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-fn comsumption(bins: &[Bin]) -> u32 {
-    let b = common_bin(bins, None);
-    let inv = b.clone().inv();
+    match std::env::args().nth(1).as_deref() {
+        Some("--report") => {
+            print!("{}", Report::new(&bins, TieBreak::Zeros));
+            return;
+        }
+        Some("--json") => {
+            println!("{}", Report::new(&bins, TieBreak::Zeros).json());
+            return;
+        }
+        _ => (),
+    }
 
-    let gamma: u32 = b.into();
-    let epsilon: u32 = inv.into();
+    if let (Some("--prefix"), Some(len)) = (std::env::args().nth(1).as_deref(), std::env::args().nth(2)) {
+        let len = len.parse().expect("prefix length");
+        let trie = Trie::new(&bins);
+        let (most, most_rows) = trie.prefix(len, |ones, zeros| most_common(ones, zeros, TieBreak::Ones));
+        let (least, least_rows) = trie.prefix(len, |ones, zeros| least_common(ones, zeros, TieBreak::Zeros));
 
-    gamma * epsilon
-}
+        println!("most common prefix: {} ({} rows)", Bin::from(most), most_rows);
+        println!("least common prefix: {} ({} rows)", Bin::from(least), least_rows);
+        return;
+    }
 
-fn oxy(bins: &[Bin]) -> u32 {
-    let mut bins = bins.to_vec();
-    let mut removes = Vec::with_capacity(bins.len());
-    let mut i = 0;
+    println!("power comsumption: {}", comsumption(&bins));
+    println!("life support: {}", life_support(&bins));
+}
 
-    loop {
-        if bins.len() == 1 {
-            break bins.remove(0).into();
-        }
-        let bin = common_bin(&bins, Some(Bit::On));
-        let bit = &bin.bits[i];
+#[derive(Debug, PartialEq)]
+enum DiagnosticError {
+    Empty,
+    Malformed(Vec<utils::LineError>),
+    Ragged { line: usize, width: usize, expected: usize },
+}
 
-        for (pos, b) in bins.iter().enumerate().rev() {
-            if &b.bits[i] != bit {
-                removes.push(pos);
+impl std::fmt::Display for DiagnosticError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DiagnosticError::Empty => write!(fmt, "no diagnostic rows"),
+            DiagnosticError::Malformed(errors) => {
+                for e in errors {
+                    writeln!(fmt, "{}", e)?;
+                }
+                Ok(())
             }
+            DiagnosticError::Ragged { line, width, expected } => write!(
+                fmt,
+                "line {}: row is {} bits wide, expected {} like the first row",
+                line, width, expected
+            ),
         }
+    }
+}
 
-        for r in removes.iter() {
-            bins.remove(*r);
-        }
+impl std::error::Error for DiagnosticError {}
 
-        removes.clear();
+fn diagnostics(content: &str) -> Result<Vec<Bin>, DiagnosticError> {
+    let bins = utils::parse_lines::<Bin>(content).map_err(DiagnosticError::Malformed)?;
+    let expected = bins.first().ok_or(DiagnosticError::Empty)?.width();
 
-        i += 1;
+    for (i, bin) in bins.iter().enumerate() {
+        if bin.width() != expected {
+            return Err(DiagnosticError::Ragged { line: i + 1, width: bin.width(), expected });
+        }
     }
+
+    Ok(bins)
 }
 
-fn co2(bins: &[Bin]) -> u32 {
-    let mut bins = bins.to_vec();
-    let mut removes = Vec::with_capacity(bins.len());
-    let mut i = 0;
+fn comsumption(bins: &[Bin]) -> Big {
+    let b = common_bin(bins, TieBreak::Zeros);
+    let inv = b.clone().inv();
 
-    loop {
-        if bins.len() == 1 {
-            break bins.remove(0).into();
-        }
-        let bin = common_bin(&bins, Some(Bit::On)).inv();
-        let bit = &bin.bits[i];
+    &Big::from(&b) * &Big::from(&inv)
+}
 
-        for (pos, b) in bins.iter().enumerate().rev() {
-            if &b.bits[i] != bit {
-                removes.push(pos);
-            }
-        }
+fn rating(bins: &[Bin], pick: impl Fn(usize, usize) -> Bit) -> Big {
+    let bin = Trie::new(bins).filter(pick).expect("no diagnostic rows");
 
-        for r in removes.iter() {
-            bins.remove(*r);
-        }
+    Big::from(bin)
+}
 
-        removes.clear();
+fn oxy(bins: &[Bin]) -> Big {
+    rating(bins, |ones, zeros| most_common(ones, zeros, TieBreak::Ones))
+}
 
-        i += 1;
-    }
+fn co2(bins: &[Bin]) -> Big {
+    rating(bins, |ones, zeros| least_common(ones, zeros, TieBreak::Zeros))
 }
 
-fn life_support(bins: &[Bin]) -> u32 {
-    oxy(bins) * co2(bins)
+fn life_support(bins: &[Bin]) -> Big {
+    &oxy(bins) * &co2(bins)
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 enum Bit {
     On,
+    #[default]
     Off
 }
 
-impl Default for Bit {
-    fn default() -> Bit {
-        Bit::Off
-    }
-}
-
 impl std::ops::Not for Bit {
     type Output = Bit;
     fn not(self) -> Bit {
@@ -98,36 +127,185 @@ impl std::ops::Not for Bit {
     }
 }
 
+// Bits are packed by numeric position: position p lives in words()[p / 64]
+// at bit p % 64, and column i (counted from the left) is position width - 1 - i.
+// Rows up to 64 bits wide stay inline.
+#[derive(Debug, PartialEq, Clone)]
+enum Words {
+    One(u64),
+    Many(Box<[u64]>),
+}
+
 #[derive(Debug, PartialEq, Clone)]
 struct Bin {
-    bits: Vec<Bit>,
+    width: usize,
+    words: Words,
 }
 
-fn common_bin(bins: &[Bin], default: Option<Bit>) -> Bin {
-    let width = bins[0].bits.len();
-    let mut one_counts = vec![0; width];
+impl Bin {
+    fn zero(width: usize) -> Bin {
+        let words = if width <= 64 {
+            Words::One(0)
+        } else {
+            Words::Many(vec![0; width.div_ceil(64)].into_boxed_slice())
+        };
 
-    for bin in bins {
-        for i in 0..width {
-            if bin.bits[i] == Bit::On {
-                one_counts[i] += 1;
-            }
+        Bin { width, words }
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn words(&self) -> &[u64] {
+        match &self.words {
+            Words::One(w) => std::slice::from_ref(w),
+            Words::Many(w) => w,
         }
     }
 
-    let mut bits = Vec::new();
+    fn words_mut(&mut self) -> &mut [u64] {
+        match &mut self.words {
+            Words::One(w) => std::slice::from_mut(w),
+            Words::Many(w) => w,
+        }
+    }
 
-    for count in one_counts {
-        if 2*count > bins.len() {
-            bits.push(Bit::On);
-        } else if 2 * count == bins.len() {
-            bits.push(default.clone().unwrap_or_default());
+    fn bit(&self, i: usize) -> Bit {
+        let p = self.width - 1 - i;
+
+        if self.words()[p / 64] >> (p % 64) & 1 == 1 {
+            Bit::On
         } else {
-            bits.push(Bit::Off);
+            Bit::Off
         }
     }
 
-    Bin { bits }
+    fn set(&mut self, i: usize, bit: Bit) {
+        let p = self.width - 1 - i;
+        let word = &mut self.words_mut()[p / 64];
+
+        match bit {
+            Bit::On => *word |= 1 << (p % 64),
+            Bit::Off => *word &= !(1 << (p % 64)),
+        }
+    }
+
+    fn bits(&self) -> impl Iterator<Item = Bit> + '_ {
+        (0..self.width).map(|i| self.bit(i))
+    }
+
+    fn inv(mut self) -> Bin {
+        let width = self.width;
+
+        for (k, word) in self.words_mut().iter_mut().enumerate() {
+            let used = (width - k * 64).min(64);
+            let mask = if used == 64 { u64::MAX } else { (1 << used) - 1 };
+
+            *word = !*word & mask;
+        }
+
+        self
+    }
+}
+
+impl std::fmt::Display for Bin {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for bit in self.bits() {
+            write!(fmt, "{}", if bit == Bit::On { '1' } else { '0' })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Vec<Bit>> for Bin {
+    fn from(bits: Vec<Bit>) -> Bin {
+        let mut bin = Bin::zero(bits.len());
+
+        for (i, bit) in bits.into_iter().enumerate() {
+            bin.set(i, bit);
+        }
+
+        bin
+    }
+}
+
+// Column-major view of a set of rows: for every numeric position a bitset
+// over the rows, so counting ones in a column is a popcount.
+struct Columns {
+    rows: usize,
+    cols: Vec<Vec<u64>>,
+}
+
+impl Columns {
+    fn new(bins: &[Bin]) -> Columns {
+        let width = bins[0].width();
+        let mut cols = vec![vec![0u64; bins.len().div_ceil(64)]; width];
+
+        for (r, bin) in bins.iter().enumerate() {
+            for (k, word) in bin.words().iter().enumerate() {
+                let mut word = *word;
+
+                while word != 0 {
+                    let p = k * 64 + word.trailing_zeros() as usize;
+                    cols[p][r / 64] |= 1 << (r % 64);
+                    word &= word - 1;
+                }
+            }
+        }
+
+        Columns { rows: bins.len(), cols }
+    }
+
+    fn ones(&self, i: usize) -> usize {
+        let p = self.cols.len() - 1 - i;
+        self.cols[p].iter().map(|w| w.count_ones() as usize).sum()
+    }
+}
+
+// which bit a column settles on when it has as many ones as zeros
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum TieBreak {
+    Ones,
+    Zeros,
+}
+
+impl TieBreak {
+    fn bit(self) -> Bit {
+        match self {
+            TieBreak::Ones => Bit::On,
+            TieBreak::Zeros => Bit::Off,
+        }
+    }
+}
+
+fn most_common(ones: usize, zeros: usize, tie: TieBreak) -> Bit {
+    match ones.cmp(&zeros) {
+        std::cmp::Ordering::Greater => Bit::On,
+        std::cmp::Ordering::Less => Bit::Off,
+        std::cmp::Ordering::Equal => tie.bit(),
+    }
+}
+
+fn least_common(ones: usize, zeros: usize, tie: TieBreak) -> Bit {
+    match ones.cmp(&zeros) {
+        std::cmp::Ordering::Greater => Bit::Off,
+        std::cmp::Ordering::Less => Bit::On,
+        std::cmp::Ordering::Equal => tie.bit(),
+    }
+}
+
+fn common_bin(bins: &[Bin], tie: TieBreak) -> Bin {
+    let columns = Columns::new(bins);
+    let mut bin = Bin::zero(bins[0].width());
+
+    for i in 0..bin.width() {
+        let ones = columns.ones(i);
+        bin.set(i, most_common(ones, columns.rows - ones, tie));
+    }
+
+    bin
 }
 
 impl utils::Parsable for Bin {
@@ -145,56 +323,45 @@ impl utils::Parsable for Bin {
             input = n;
         }
 
-        Ok((input, Bin { bits }))
-    }
-}
-
-impl Bin {
-    fn inv(mut self) -> Bin {
-        for i in 0..self.bits.len() {
-            self.bits[i] = match self.bits[i] {
-                Bit::On => Bit::Off,
-                Bit::Off => Bit::On,
-            };
+        if bits.is_empty() {
+            return Err(nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Many1)));
         }
 
-        Bin { bits: self.bits }
+        Ok((input, Bin::from(bits)))
     }
 }
 
-impl Into<u32> for Bin {
-    fn into(self) -> u32 {
-        let mut res = 0;
-        for bit in self.bits {
-            res <<= 1;
+impl From<&Bin> for Big {
+    fn from(bin: &Bin) -> Big {
+        Big::from_limbs(bin.words())
+    }
+}
 
-            if bit == Bit::On {
-                res += 1;
-            }
-        }
+impl TryFrom<Bin> for u32 {
+    type Error = std::num::TryFromIntError;
 
-        res
+    fn try_from(bin: Bin) -> Result<u32, Self::Error> {
+        u128::try_from(&Big::from(&bin)).unwrap_or(u128::MAX).try_into()
     }
 }
 
-
 #[test]
 fn test_model() {
     let expected = vec![
-        Bin { bits: vec![Bit::Off, Bit::Off, Bit::On, Bit::Off, Bit::Off] },
-        Bin { bits: vec![Bit::On, Bit::On, Bit::On, Bit::On, Bit::Off] },
-        Bin { bits: vec![Bit::On, Bit::Off, Bit::On, Bit::On, Bit::Off] },
-        Bin { bits: vec![Bit::On, Bit::Off, Bit::On, Bit::On, Bit::On] },
-
-        Bin { bits: vec![Bit::On, Bit::Off, Bit::On, Bit::Off, Bit::On] },
-        Bin { bits: vec![Bit::Off, Bit::On, Bit::On, Bit::On, Bit::On] },
-        Bin { bits: vec![Bit::Off, Bit::Off, Bit::On, Bit::On, Bit::On] },
-        Bin { bits: vec![Bit::On, Bit::On, Bit::On, Bit::Off, Bit::Off] },
-
-        Bin { bits: vec![Bit::On, Bit::Off, Bit::Off, Bit::Off, Bit::Off] },
-        Bin { bits: vec![Bit::On, Bit::On, Bit::Off, Bit::Off, Bit::On] },
-        Bin { bits: vec![Bit::Off, Bit::Off, Bit::Off, Bit::On, Bit::Off] },
-        Bin { bits: vec![Bit::Off, Bit::On, Bit::Off, Bit::On, Bit::Off] },
+        Bin::from(vec![Bit::Off, Bit::Off, Bit::On, Bit::Off, Bit::Off]),
+        Bin::from(vec![Bit::On, Bit::On, Bit::On, Bit::On, Bit::Off]),
+        Bin::from(vec![Bit::On, Bit::Off, Bit::On, Bit::On, Bit::Off]),
+        Bin::from(vec![Bit::On, Bit::Off, Bit::On, Bit::On, Bit::On]),
+
+        Bin::from(vec![Bit::On, Bit::Off, Bit::On, Bit::Off, Bit::On]),
+        Bin::from(vec![Bit::Off, Bit::On, Bit::On, Bit::On, Bit::On]),
+        Bin::from(vec![Bit::Off, Bit::Off, Bit::On, Bit::On, Bit::On]),
+        Bin::from(vec![Bit::On, Bit::On, Bit::On, Bit::Off, Bit::Off]),
+
+        Bin::from(vec![Bit::On, Bit::Off, Bit::Off, Bit::Off, Bit::Off]),
+        Bin::from(vec![Bit::On, Bit::On, Bit::Off, Bit::Off, Bit::On]),
+        Bin::from(vec![Bit::Off, Bit::Off, Bit::Off, Bit::On, Bit::Off]),
+        Bin::from(vec![Bit::Off, Bit::On, Bit::Off, Bit::On, Bit::Off]),
     ];
 
     let actual = utils::test_input_vec::<Bin>();
@@ -206,8 +373,10 @@ fn test_model() {
 
 #[test]
 fn bin_2_u32() {
-    let bin = Bin { bits: vec![Bit::On, Bit::Off, Bit::Off, Bit::On, Bit::Off] };
-    let num: u32 = bin.into();
+    let bin = Bin::from(vec![Bit::On, Bit::Off, Bit::Off, Bit::On, Bit::Off]);
+    assert_eq!(bin.to_string(), "10010");
+
+    let num = u32::try_from(bin).unwrap();
 
     assert_eq!(num, 18_u32);
 }
@@ -216,13 +385,110 @@ fn bin_2_u32() {
 fn test_output() {
     let bin = utils::test_input_vec::<Bin>();
 
-    assert_eq!(comsumption(&bin), 198);
+    assert_eq!(comsumption(&bin), Big::from(198u64));
 }
 
 #[test]
 fn test_output2() {
     let bin = utils::test_input_vec::<Bin>();
 
-    assert_eq!(life_support(&bin), 230);
+    assert_eq!(life_support(&bin), Big::from(230u64));
+}
+
+
+#[test]
+fn test_wide_output() {
+    let rows = ["10".repeat(50), "01".repeat(50), "1".to_string() + &"0".repeat(99), "11".to_string() + &"0".repeat(98)];
+    let bins = diagnostics(&rows.join("\n")).unwrap();
+
+    assert_eq!(
+        comsumption(&bins).to_string(),
+        "401734511064747568885490523084656825330436633744949857222656"
+    );
+    assert_eq!(
+        life_support(&bins).to_string(),
+        "357097343168664505675991576075250511404832563328844317531250"
+    );
+    assert!(u32::try_from(bins[0].clone()).is_err());
+}
+
+#[test]
+fn test_tie_break() {
+    assert_eq!(most_common(2, 2, TieBreak::Ones), Bit::On);
+    assert_eq!(most_common(2, 2, TieBreak::Zeros), Bit::Off);
+    assert_eq!(least_common(2, 2, TieBreak::Zeros), Bit::Off);
+    assert_eq!(most_common(3, 2, TieBreak::Zeros), Bit::On);
+    assert_eq!(least_common(3, 2, TieBreak::Ones), Bit::Off);
+
+    let bins = vec![Bin::from(vec![Bit::On, Bit::Off]), Bin::from(vec![Bit::Off, Bit::Off])];
+    assert_eq!(common_bin(&bins, TieBreak::Ones).to_string(), "10");
+    assert_eq!(common_bin(&bins, TieBreak::Zeros).to_string(), "00");
 }
 
+#[test]
+fn test_ragged() {
+    assert_eq!(
+        diagnostics("101\n1100\n011\n"),
+        Err(DiagnosticError::Ragged { line: 2, width: 4, expected: 3 })
+    );
+    assert_eq!(
+        diagnostics("101\n1x1\n"),
+        Err(DiagnosticError::Malformed(vec![utils::LineError { line: 2, content: "1x1".to_string() }]))
+    );
+    assert_eq!(diagnostics(""), Err(DiagnosticError::Empty));
+}
+
+#[cfg(test)]
+fn generate(rows: usize, width: usize) -> Vec<Bin> {
+    let mut rng = utils::rng::Rng::new(0x2545_f491_4f6c_dd1d);
+
+    (0..rows)
+        .map(|_| Bin::from((0..width).map(|_| if rng.next_u64() & 1 == 1 { Bit::On } else { Bit::Off }).collect::<Vec<_>>()))
+        .collect()
+}
+
+#[test]
+fn test_wide_common() {
+    let bins = generate(301, 130);
+    let common = common_bin(&bins, TieBreak::Zeros);
+
+    for i in 0..130 {
+        let ones = bins.iter().filter(|b| b.bit(i) == Bit::On).count();
+        assert_eq!(common.bit(i) == Bit::On, 2 * ones > bins.len(), "column {}", i);
+    }
+
+    let inv = common.clone().inv();
+    assert!((0..130).all(|i| inv.bit(i) == !common.bit(i)));
+}
+
+// cargo test --release -- --ignored --nocapture bench_common_bin
+#[test]
+#[ignore]
+fn bench_common_bin() {
+    use std::time::Instant;
+
+    let bins = generate(1_000_000, 48);
+
+    // the previous representation: one enum per bit, compared column by column
+    let unpacked = bins.iter().map(|b| b.bits().collect::<Vec<_>>()).collect::<Vec<_>>();
+    let start = Instant::now();
+    let mut one_counts = vec![0; 48];
+    for bits in &unpacked {
+        for (count, bit) in one_counts.iter_mut().zip(bits) {
+            if *bit == Bit::On {
+                *count += 1;
+            }
+        }
+    }
+    let unpacked_time = start.elapsed();
+
+    let start = Instant::now();
+    let common = common_bin(&bins, TieBreak::Zeros);
+    let packed_time = start.elapsed();
+
+    for (i, count) in one_counts.iter().enumerate() {
+        assert_eq!(common.bit(i) == Bit::On, 2 * count > bins.len());
+    }
+
+    println!("unpacked: {:?}, packed: {:?}", unpacked_time, packed_time);
+}
@@ -0,0 +1,131 @@
+use utils::big::Big;
+use crate::{most_common, Bin, Bit, Columns, TieBreak};
+
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ColumnReport {
+    pub ones: usize,
+    pub zeros: usize,
+    pub majority: Bit,
+    pub tie: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Report {
+    pub tie_break: TieBreak,
+    pub columns: Vec<ColumnReport>,
+    pub gamma: Bin,
+    pub epsilon: Bin,
+}
+
+fn digit(bit: Bit) -> char {
+    match bit {
+        Bit::On => '1',
+        Bit::Off => '0',
+    }
+}
+
+impl Report {
+    pub fn new(bins: &[Bin], tie_break: TieBreak) -> Report {
+        let counts = Columns::new(bins);
+        let width = bins[0].width();
+        let mut gamma = Bin::zero(width);
+
+        let columns = (0..width)
+            .map(|i| {
+                let ones = counts.ones(i);
+                let zeros = counts.rows - ones;
+                let majority = most_common(ones, zeros, tie_break);
+
+                gamma.set(i, majority);
+                ColumnReport { ones, zeros, majority, tie: ones == zeros }
+            })
+            .collect();
+
+        let epsilon = gamma.clone().inv();
+
+        Report { tie_break, columns, gamma, epsilon }
+    }
+
+    pub fn json(&self) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                format!(
+                    "{{\"column\":{},\"ones\":{},\"zeros\":{},\"majority\":{},\"tie\":{}}}",
+                    i,
+                    c.ones,
+                    c.zeros,
+                    digit(c.majority),
+                    c.tie
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"tie_break\":\"{}\",\"columns\":[{}],\"gamma\":\"{}\",\"epsilon\":\"{}\",\
+             \"gamma_rate\":\"{}\",\"epsilon_rate\":\"{}\"}}",
+            match self.tie_break {
+                TieBreak::Ones => "ones",
+                TieBreak::Zeros => "zeros",
+            },
+            columns,
+            self.gamma,
+            self.epsilon,
+            Big::from(&self.gamma),
+            Big::from(&self.epsilon),
+        )
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "{:>6} {:>8} {:>8} {:>8} {:>4}", "column", "ones", "zeros", "majority", "tie")?;
+
+        for (i, c) in self.columns.iter().enumerate() {
+            let tie = if c.tie { "yes" } else { "" };
+            writeln!(fmt, "{:>6} {:>8} {:>8} {:>8} {:>4}", i, c.ones, c.zeros, digit(c.majority), tie)?;
+        }
+
+        writeln!(fmt)?;
+        writeln!(fmt, "gamma:   {} ({})", self.gamma, Big::from(&self.gamma))?;
+        writeln!(fmt, "epsilon: {} ({})", self.epsilon, Big::from(&self.epsilon))
+    }
+}
+
+#[test]
+fn test_report() {
+    let report = Report::new(&utils::test_input_vec::<Bin>(), TieBreak::Zeros);
+
+    assert_eq!(report.columns[0], ColumnReport { ones: 7, zeros: 5, majority: Bit::On, tie: false });
+    assert_eq!(report.columns[1], ColumnReport { ones: 5, zeros: 7, majority: Bit::Off, tie: false });
+    assert_eq!(report.gamma.to_string(), "10110");
+    assert_eq!(report.epsilon.to_string(), "01001");
+    assert_eq!(
+        report.json(),
+        "{\"tie_break\":\"zeros\",\"columns\":[\
+         {\"column\":0,\"ones\":7,\"zeros\":5,\"majority\":1,\"tie\":false},\
+         {\"column\":1,\"ones\":5,\"zeros\":7,\"majority\":0,\"tie\":false},\
+         {\"column\":2,\"ones\":8,\"zeros\":4,\"majority\":1,\"tie\":false},\
+         {\"column\":3,\"ones\":7,\"zeros\":5,\"majority\":1,\"tie\":false},\
+         {\"column\":4,\"ones\":5,\"zeros\":7,\"majority\":0,\"tie\":false}],\
+         \"gamma\":\"10110\",\"epsilon\":\"01001\",\"gamma_rate\":\"22\",\"epsilon_rate\":\"9\"}"
+    );
+}
+
+#[test]
+fn test_report_tie() {
+    let bins = vec![Bin::from(vec![Bit::On, Bit::On]), Bin::from(vec![Bit::Off, Bit::On])];
+
+    let report = Report::new(&bins, TieBreak::Ones);
+    assert!(report.columns[0].tie);
+    assert!(!report.columns[1].tie);
+    assert_eq!(report.gamma.to_string(), "11");
+
+    let report = Report::new(&bins, TieBreak::Zeros);
+    assert_eq!(report.gamma.to_string(), "01");
+}
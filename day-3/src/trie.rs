@@ -0,0 +1,156 @@
+use crate::{Bin, Bit};
+
+#[cfg(test)]
+use crate::{least_common, most_common, TieBreak};
+
+const NONE: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+struct Node {
+    children: [usize; 2],
+    count: usize,
+    // a row ending at this node, only set on leaves
+    row: usize,
+}
+
+impl Node {
+    fn new() -> Node {
+        Node { children: [NONE; 2], count: 0, row: NONE }
+    }
+}
+
+// Binary trie over the columns of a diagnostic, where every node knows how
+// many rows share its prefix. Rating filters become a single walk from the
+// root instead of repeated scans over the remaining rows.
+pub struct Trie<'a> {
+    bins: &'a [Bin],
+    nodes: Vec<Node>,
+    width: usize,
+}
+
+fn slot(bit: Bit) -> usize {
+    match bit {
+        Bit::Off => 0,
+        Bit::On => 1,
+    }
+}
+
+impl<'a> Trie<'a> {
+    pub fn new(bins: &'a [Bin]) -> Trie<'a> {
+        let width = bins.first().map_or(0, |b| b.width());
+        let mut nodes = vec![Node::new()];
+
+        for (row, bin) in bins.iter().enumerate() {
+            let mut cur = 0;
+            nodes[cur].count += 1;
+
+            for bit in bin.bits() {
+                let s = slot(bit);
+
+                if nodes[cur].children[s] == NONE {
+                    nodes[cur].children[s] = nodes.len();
+                    nodes.push(Node::new());
+                }
+
+                cur = nodes[cur].children[s];
+                nodes[cur].count += 1;
+            }
+
+            if nodes[cur].row == NONE {
+                nodes[cur].row = row;
+            }
+        }
+
+        Trie { bins, nodes, width }
+    }
+
+    fn count(&self, node: usize, s: usize) -> usize {
+        match self.nodes[node].children[s] {
+            NONE => 0,
+            child => self.nodes[child].count,
+        }
+    }
+
+    // Follows `pick(ones, zeros)` for up to `len` columns. Once a single row
+    // is left, or the picked side is empty, the walk takes the only
+    // remaining branch. Returns the chosen prefix and its node.
+    fn walk(&self, len: usize, pick: &impl Fn(usize, usize) -> Bit) -> (Vec<Bit>, usize) {
+        let mut prefix = Vec::with_capacity(len);
+        let mut cur = 0;
+
+        if self.nodes[cur].count == 0 {
+            return (prefix, cur);
+        }
+
+        for _ in 0..len.min(self.width) {
+            let zeros = self.count(cur, 0);
+            let ones = self.count(cur, 1);
+
+            let mut bit = pick(ones, zeros);
+
+            if self.count(cur, slot(bit)) == 0 {
+                bit = !bit;
+            }
+
+            prefix.push(bit);
+            cur = self.nodes[cur].children[slot(bit)];
+        }
+
+        (prefix, cur)
+    }
+
+    // The row left over after filtering on every column with `pick`.
+    pub fn filter(&self, pick: impl Fn(usize, usize) -> Bit) -> Option<&'a Bin> {
+        let (_, node) = self.walk(self.width, &pick);
+
+        match self.nodes[node].row {
+            NONE => None,
+            row => Some(&self.bins[row]),
+        }
+    }
+
+    // The first `len` bits selected by `pick`, with how many rows share them.
+    pub fn prefix(&self, len: usize, pick: impl Fn(usize, usize) -> Bit) -> (Vec<Bit>, usize) {
+        let (prefix, node) = self.walk(len, &pick);
+
+        (prefix, self.nodes[node].count)
+    }
+}
+
+#[cfg(test)]
+fn bits(s: &str) -> Vec<Bit> {
+    s.chars().map(|c| if c == '1' { Bit::On } else { Bit::Off }).collect()
+}
+
+#[test]
+fn test_filter() {
+    let bins = utils::test_input_vec::<Bin>();
+    let trie = Trie::new(&bins);
+
+    let oxy = trie.filter(|ones, zeros| most_common(ones, zeros, TieBreak::Ones));
+    let co2 = trie.filter(|ones, zeros| least_common(ones, zeros, TieBreak::Zeros));
+
+    assert_eq!(oxy, Some(&Bin::from(bits("10111"))));
+    assert_eq!(co2, Some(&Bin::from(bits("01010"))));
+}
+
+#[test]
+fn test_prefix() {
+    let bins = utils::test_input_vec::<Bin>();
+    let trie = Trie::new(&bins);
+
+    assert_eq!(trie.prefix(0, |_, _| Bit::On), (vec![], 12));
+    assert_eq!(trie.prefix(2, |ones, zeros| most_common(ones, zeros, TieBreak::Ones)), (bits("10"), 4));
+    assert_eq!(trie.prefix(3, |_, _| Bit::Off), (bits("000"), 1));
+}
+
+#[test]
+fn test_uniform_column() {
+    let bins = vec![Bin::from(bits("110")), Bin::from(bits("100")), Bin::from(bits("101"))];
+    let trie = Trie::new(&bins);
+
+    // every row starts with 1, so even the least common pick has to keep them
+    let least = trie.filter(|ones, zeros| least_common(ones, zeros, TieBreak::Zeros));
+    assert_eq!(least, Some(&bins[0]));
+    assert_eq!(Trie::new(&[]).filter(|_, _| Bit::On), None);
+}
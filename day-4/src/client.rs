@@ -0,0 +1,95 @@
+use crate::server::{send, Message, Outcome};
+use crate::{Board, Layout, Mark};
+
+use std::io::{self, BufRead, BufReader, Lines, Write};
+use std::net::TcpStream;
+use std::rc::Rc;
+
+pub struct Client {
+    stream: TcpStream,
+    lines: Lines<BufReader<TcpStream>>,
+    board: Board,
+    patterns: Vec<Vec<usize>>,
+    drawn: Vec<u32>,
+}
+
+fn protocol_error(msg: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+impl Client {
+    // Joins the game at `addr` and waits for a board to be dealt.
+    pub fn connect(addr: &str, name: &str) -> io::Result<Client> {
+        let stream = TcpStream::connect(addr)?;
+        let mut lines = BufReader::new(stream.try_clone()?).lines();
+
+        send(&stream, &Message::Join(name.to_string()))?;
+
+        let reply = lines.next().ok_or_else(|| protocol_error("server closed the connection"))??;
+
+        match reply.parse().map_err(protocol_error)? {
+            Message::Board(board, patterns) => Ok(Client { stream, lines, board, patterns, drawn: Vec::new() }),
+            Message::Err(e) => Err(io::Error::other(e)),
+            msg => Err(protocol_error(format!("expected a board, got {}", msg))),
+        }
+    }
+
+    // a second handle for claiming from another thread
+    pub fn stream(&self) -> io::Result<TcpStream> {
+        self.stream.try_clone()
+    }
+
+    // Marked like the server does: only the first cell holding a number, and
+    // with the patterns the server sent.
+    fn mark(&self) -> Mark<'_> {
+        let layout = Layout::from_patterns(self.patterns.clone(), self.board.numbers.len());
+        let mut mark = Mark::with_layout(&self.board, Rc::new(layout));
+
+        for n in &self.drawn {
+            if let Some(cell) = self.board.numbers.iter().position(|b| b == n) {
+                mark.mark_cell(cell);
+            }
+        }
+
+        mark
+    }
+
+    // Follows the game until it ends, printing the board after every draw.
+    // With `auto` a pattern claims itself as soon as it completes, and again
+    // on the next draw if the server turned the claim down.
+    pub fn run(&mut self, auto: bool, mut out: impl Write) -> io::Result<Outcome> {
+        let mut claimed = false;
+        let mut winner = None;
+
+        write!(out, "{}", self.mark())?;
+
+        while let Some(line) = self.lines.next() {
+            match line?.parse().map_err(protocol_error)? {
+                Message::Draw(i, number) => {
+                    self.drawn.push(number);
+
+                    let mark = self.mark();
+                    writeln!(out, "\ndraw {}: {}", i + 1, number)?;
+                    write!(out, "{}", mark)?;
+
+                    if auto && !claimed && mark.done() {
+                        claimed = true;
+                        send(&self.stream, &Message::Bingo)?;
+                    }
+                }
+                Message::Winner(name, score) => {
+                    writeln!(out, "\nbingo! {} wins with {}", name, score)?;
+                    winner = Some((name, score));
+                }
+                Message::Err(e) => {
+                    claimed = false;
+                    writeln!(out, "server: {}", e)?;
+                }
+                Message::End => break,
+                msg => return Err(protocol_error(format!("unexpected {}", msg))),
+            }
+        }
+
+        Ok(winner)
+    }
+}
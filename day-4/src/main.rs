@@ -1,161 +1,354 @@
 use nom::{
     bytes::complete::tag,
-    character::complete::{digit1, multispace0, multispace1},
+    character::complete::{digit1, newline, space0, space1},
     combinator::map_res,
     multi::separated_list1,
-    sequence::{preceded, terminated, tuple},
+    sequence::preceded,
     IResult,
 };
 
+mod client;
+mod rule;
+mod server;
+mod timeline;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use client::Client;
+use rule::{Mask, WinRule};
+use server::{send, Message, Server};
+use timeline::{Leaderboard, Timeline};
+
 fn main() {
-    let game = utils::input::<Game>();
-    println!("winning score: {}", game.run());
-    println!("let the squidy win: {}", game.last_run());
+    let mut args = std::env::args().skip(1).peekable();
+
+    if args.peek().map(String::as_str) == Some("client") {
+        args.next();
+        return client(args);
+    }
+
+    let serve = args.next_if(|arg| arg == "serve").is_some();
+    let mut game = utils::input::<Game>();
+
+    let mut leaderboard = false;
+    let mut addr = ADDR.to_string();
+    let mut players = 1;
+    let mut interval = 1000;
+
+    while let Some(arg) = args.next() {
+        let rule = match arg.as_str() {
+            "--leaderboard" => {
+                leaderboard = true;
+                continue;
+            }
+            "--addr" if serve => {
+                addr = args.next().unwrap_or_else(|| fail(usage()));
+                continue;
+            }
+            "--players" if serve => {
+                players = args.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| fail(usage()));
+                continue;
+            }
+            "--interval" if serve => {
+                interval = args.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| fail(usage()));
+                continue;
+            }
+            "--rule" => args.next().ok_or_else(usage).and_then(|rule| rule.parse()),
+            "--mask" => args.next().ok_or_else(usage).and_then(|file| {
+                std::fs::read_to_string(&file)
+                    .map(|text| WinRule::Masks(vec![Mask::parse(&text)]))
+                    .map_err(|e| format!("{}: {}", file, e))
+            }),
+            _ => Err(usage()),
+        };
+
+        match rule {
+            Ok(rule) => game = game.with_rule(rule),
+            Err(e) => fail(e),
+        }
+    }
+
+    if serve {
+        if players > game.boards.len() {
+            fail(format!("{} player(s) but only {} board(s)", players, game.boards.len()));
+        }
+
+        let listener = std::net::TcpListener::bind(&addr).unwrap_or_else(|e| fail(format!("{}: {}", addr, e)));
+        println!("waiting for {} player(s) on {}", players, addr);
+
+        let server = Server::new(game)
+            .with_players(players)
+            .with_interval(std::time::Duration::from_millis(interval));
+
+        match server.serve(listener) {
+            Ok(Some((name, score))) => println!("{} wins with {}", name, score),
+            Ok(None) => println!("nobody called bingo"),
+            Err(e) => fail(e),
+        }
+
+        return;
+    }
+
+    if leaderboard {
+        print!("{}", Leaderboard::new(&game));
+        return;
+    }
+
+    // with some rules no board can ever win
+    match (game.run(), game.last_run()) {
+        (Some(first), Some(last)) => {
+            println!("winning score: {}", first);
+            println!("let the squidy win: {}", last);
+        }
+        _ => println!("no winner"),
+    }
+}
+
+const ADDR: &str = "127.0.0.1:7878";
+
+fn client(mut args: impl Iterator<Item = String>) {
+    let name = args.next().filter(|n| !n.starts_with("--")).unwrap_or_else(|| fail(usage()));
+    let mut addr = ADDR.to_string();
+    let mut auto = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--addr" => addr = args.next().unwrap_or_else(|| fail(usage())),
+            "--auto" => auto = true,
+            _ => fail(usage()),
+        }
+    }
+
+    let mut client = Client::connect(&addr, &name).unwrap_or_else(|e| fail(format!("{}: {}", addr, e)));
+    let claims = client.stream().unwrap_or_else(|e| fail(e));
+
+    // type "bingo" to claim, "quit" to leave
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            let msg = match line.trim() {
+                "bingo" => Message::Bingo,
+                "quit" => Message::Quit,
+                _ => continue,
+            };
+
+            if send(&claims, &msg).is_err() {
+                break;
+            }
+        }
+    });
+
+    if let Err(e) = client.run(auto, std::io::stdout()) {
+        fail(e);
+    }
+}
+
+fn fail(e: impl std::fmt::Display) -> ! {
+    eprintln!("{}", e);
+    std::process::exit(1);
+}
+
+fn usage() -> String {
+    [
+        "usage: day-4 [--rule lines|diagonals|corners|blackout|x[+...]] [--mask file] [--leaderboard]",
+        "       day-4 serve [--addr host:port] [--players n] [--interval ms] [--rule ...] [--mask file]",
+        "       day-4 client <name> [--addr host:port] [--auto]",
+    ]
+    .join("\n")
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Game {
-    seq: Vec<u8>,
+    seq: Vec<u32>,
     boards: Vec<Board>,
+    rule: WinRule,
+    // drawn number -> (board, cell) for the first cell holding it on each board
+    index: HashMap<u32, Vec<(usize, usize)>>,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Board {
-    numbers: [u8; 25],
+    rows: usize,
+    cols: usize,
+    numbers: Vec<u32>,
+}
+
+// Winning patterns for one board size, plus which patterns each cell is in.
+#[derive(PartialEq, Debug)]
+pub struct Layout {
+    patterns: Vec<Vec<usize>>,
+    by_cell: Vec<Vec<usize>>,
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Mark<'a> {
     board: &'a Board,
-    marked: [bool; 25],
+    layout: Rc<Layout>,
+    marked: Vec<bool>,
+    hits: Vec<usize>,
+    unmarked: u64,
+    // first pattern to be completed
+    line: Option<usize>,
 }
 
 impl Game {
-    fn run(&self) -> u32 {
-        let mut marks = self.boards.iter().map(|b| Mark::new(b)).collect::<Vec<_>>();
+    fn new(seq: Vec<u32>, boards: Vec<Board>) -> Game {
+        let mut index: HashMap<u32, Vec<(usize, usize)>> = HashMap::new();
 
-        for s in self.seq.iter() {
-            for m in &mut marks {
-                m.mark(*s);
-            }
+        for (b, board) in boards.iter().enumerate() {
+            for (cell, num) in board.numbers.iter().enumerate() {
+                let entries = index.entry(*num).or_default();
 
-            for m in &marks {
-                if m.done() {
-                    return *s as u32 * m.unmarked_sum();
+                if entries.last().is_none_or(|(last, _)| *last != b) {
+                    entries.push((b, cell));
                 }
             }
         }
 
-        unreachable!()
+        Game { seq, boards, rule: WinRule::default(), index }
     }
 
-    fn last_run(&self) -> u32 {
-        let mut marks = self.boards.iter().map(|b| Mark::new(b)).collect::<Vec<_>>();
-        let mut seqs = self.seq.iter();
+    fn with_rule(mut self, rule: WinRule) -> Game {
+        self.rule = rule;
+        self
+    }
 
-        while let Some(s) = seqs.next() {
-            for m in &mut marks {
-                m.mark(*s);
-            }
+    fn marks(&self) -> Vec<Mark<'_>> {
+        let mut layouts: HashMap<(usize, usize), Rc<Layout>> = HashMap::new();
 
-            if marks.len() == 1 && marks[0].done() {
-                return *s as u32 * marks.remove(0).unmarked_sum();
-            }
+        self.boards
+            .iter()
+            .map(|b| {
+                let layout = layouts
+                    .entry((b.rows, b.cols))
+                    .or_insert_with(|| Rc::new(Layout::new(&self.rule, b.rows, b.cols)));
 
-            marks.retain(|m| !m.done());
-        }
+                Mark::with_layout(b, layout.clone())
+            })
+            .collect()
+    }
+
+    fn draws(&self, num: u32) -> &[(usize, usize)] {
+        self.index.get(&num).map_or(&[], |v| v)
+    }
 
-        unreachable!()
+    fn timeline(&self) -> Timeline<'_> {
+        Timeline::new(self)
+    }
+
+    fn run(&self) -> Option<u128> {
+        self.timeline().next().map(|w| w.score)
+    }
+
+    fn last_run(&self) -> Option<u128> {
+        self.timeline().last().map(|w| w.score)
     }
 }
 
-impl<'a> Mark<'a> {
-    fn new(board: &'a Board) -> Mark {
-        Mark {
-            board,
-            marked: [false; 25]
+impl Board {
+    fn from_rows(rows: Vec<Vec<u32>>) -> Result<Board, String> {
+        let cols = rows[0].len();
+
+        if let Some(i) = rows.iter().position(|r| r.len() != cols) {
+            return Err(format!("board row {} has {} numbers, expected {}", i + 1, rows[i].len(), cols));
         }
+
+        Ok(Board {
+            rows: rows.len(),
+            cols,
+            numbers: rows.into_iter().flatten().collect(),
+        })
+    }
+}
+
+impl Layout {
+    fn new(rule: &WinRule, rows: usize, cols: usize) -> Layout {
+        Layout::from_patterns(rule.patterns(rows, cols), rows * cols)
     }
 
-    fn mark(&mut self, num: u8) {
-        for i in 0..25 {
-            if *&self.board.numbers[i] == num {
-                self.marked[i] = true;
-                return;
+    fn from_patterns(patterns: Vec<Vec<usize>>, cells: usize) -> Layout {
+        let mut by_cell = vec![Vec::new(); cells];
+
+        for (p, pattern) in patterns.iter().enumerate() {
+            for cell in pattern {
+                by_cell[*cell].push(p);
             }
         }
+
+        Layout { patterns, by_cell }
     }
+}
 
-    fn done(&self) -> bool {
-        for row in 0..5 {
-            let mut works = true;
+impl<'a> Mark<'a> {
+    fn new(board: &'a Board, rule: &WinRule) -> Mark<'a> {
+        Mark::with_layout(board, Rc::new(Layout::new(rule, board.rows, board.cols)))
+    }
 
-            for col in 0..5 {
-                if !self.marked[row * 5 + col] {
-                    works = false;
-                }
-            }
+    fn with_layout(board: &'a Board, layout: Rc<Layout>) -> Mark<'a> {
+        Mark {
+            board,
+            marked: vec![false; board.numbers.len()],
+            hits: vec![0; layout.patterns.len()],
+            unmarked: board.numbers.iter().map(|n| *n as u64).sum(),
+            line: None,
+            layout,
+        }
+    }
 
-            if works {
-                return true;
-            }
+    fn mark_cell(&mut self, cell: usize) {
+        if self.marked[cell] {
+            return;
         }
 
-        for col in 0..5 {
-            let mut works = true;
+        self.marked[cell] = true;
+        self.unmarked -= self.board.numbers[cell] as u64;
 
-            for row in 0..5 {
-                if !self.marked[row * 5 + col] {
-                    works = false;
-                }
-            }
+        for p in &self.layout.by_cell[cell] {
+            self.hits[*p] += 1;
 
-            if works {
-                return true;
+            if self.hits[*p] == self.layout.patterns[*p].len() && self.line.is_none() {
+                self.line = Some(*p);
             }
         }
-
-        false
     }
 
-    fn unmarked_sum(&self) -> u32 {
-        let mut res = 0;
+    fn done(&self) -> bool {
+        self.line.is_some()
+    }
 
-        for i in 0..25 {
-            if !self.marked[i] {
-                res += self.board.numbers[i] as u32;
-            }
-        }
+    fn winning_line(&self) -> Option<&[usize]> {
+        self.line.map(|p| &self.layout.patterns[p][..])
+    }
 
-        res
+    fn unmarked_sum(&self) -> u64 {
+        self.unmarked
     }
 }
 
 impl<'a> std::fmt::Display for Mark<'a> {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for r in 0..5 {
-            for c in 0..4 {
-                if self.marked[r * 5 + c] {
-                    write!(fmt, "\x1b[31m{:>2}\x1b[0m ", self.board.numbers[r * 5 + c])?;
+        let Board { rows, cols, .. } = *self.board;
+        let width = self.board.numbers.iter().map(|n| n.to_string().len()).max().unwrap_or(1);
+
+        for r in 0..rows {
+            for c in 0..cols {
+                let i = r * cols + c;
+                let sep = if c + 1 == cols { "\n" } else { " " };
+
+                if self.marked[i] {
+                    write!(fmt, "\x1b[31m{:>width$}\x1b[0m{}", self.board.numbers[i], sep)?;
                 } else {
-                    write!(fmt, "{:>2} ", self.board.numbers[r * 5 + c])?;
+                    write!(fmt, "{:>width$}{}", self.board.numbers[i], sep)?;
                 }
             }
-
-            let c = 4;
-            if self.marked[r * 5 + c] {
-                write!(fmt, "\x1b[31m{:>2}\x1b[0m\n", self.board.numbers[r * 5 + c])?;
-            } else {
-                write!(fmt, "{:>2}\n", self.board.numbers[r * 5 + c])?;
-            }
         }
 
         Ok(())
     }
 }
 
-fn num(input: &str) -> IResult<&str, u8> {
-    map_res(digit1, |num: &str| num.parse::<u8>())(input)
+fn num(input: &str) -> IResult<&str, u32> {
+    map_res(digit1, |num: &str| num.parse::<u32>())(input)
 }
 
 impl utils::Parsable for Game {
@@ -164,62 +357,15 @@ impl utils::Parsable for Game {
         let (input, _) = tag("\n\n")(input)?;
         let (input, boards) = separated_list1(tag("\n\n"), Board::parse)(input)?;
 
-        Ok((input, Game { seq, boards }))
+        Ok((input, Game::new(seq, boards)))
     }
 }
 
 impl utils::Parsable for Board {
     fn parse(input: &str) -> IResult<&str, Board> {
-        let (input, row1) = tuple((
-            preceded(multispace0, terminated(num, multispace1)),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-        ))(input)?;
-
-        let (input, row2) = tuple((
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-        ))(input)?;
-
-        let (input, row3) = tuple((
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-        ))(input)?;
-
-        let (input, row4) = tuple((
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-        ))(input)?;
-
-        let (input, row5) = tuple((
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            terminated(num, multispace1),
-            num,
-        ))(input)?;
-
-        Ok((
-            input,
-            Board {
-                numbers: [
-                    row1.0, row1.1, row1.2, row1.3, row1.4, row2.0, row2.1, row2.2, row2.3, row2.4,
-                    row3.0, row3.1, row3.2, row3.3, row3.4, row4.0, row4.1, row4.2, row4.3, row4.4,
-                    row5.0, row5.1, row5.2, row5.3, row5.4,
-                ],
-            },
-        ))
+        let row = preceded(space0, separated_list1(space1, num));
+
+        map_res(separated_list1(newline, row), Board::from_rows)(input)
     }
 }
 
@@ -229,32 +375,38 @@ fn test_input() {
 
     assert_eq!(
         game,
-        Game {
-            seq: vec![
+        Game::new(
+            vec![
                 7, 4, 9, 5, 11, 17, 23, 2, 0, 14, 21, 24, 10, 16, 13, 6, 15, 25, 12, 22, 18, 20, 8,
                 19, 3, 26, 1
             ],
-            boards: vec![
+            vec![
                 Board {
-                    numbers: [
+                    rows: 5,
+                    cols: 5,
+                    numbers: vec![
                         22, 13, 17, 11, 0, 8, 2, 23, 4, 24, 21, 9, 14, 16, 7, 6, 10, 3, 18, 5, 1,
                         12, 20, 15, 19,
                     ],
                 },
                 Board {
-                    numbers: [
+                    rows: 5,
+                    cols: 5,
+                    numbers: vec![
                         3, 15, 0, 2, 22, 9, 18, 13, 17, 5, 19, 8, 7, 25, 23, 20, 11, 10, 24, 4, 14,
                         21, 16, 12, 6,
                     ],
                 },
                 Board {
-                    numbers: [
+                    rows: 5,
+                    cols: 5,
+                    numbers: vec![
                         14, 21, 17, 24, 4, 10, 16, 15, 9, 19, 18, 8, 23, 26, 20, 22, 11, 13, 6, 5,
                         2, 0, 12, 3, 7,
                     ],
                 }
             ]
-        }
+        )
     );
 }
 
@@ -262,12 +414,151 @@ fn test_input() {
 fn test_output() {
     let game = utils::test_input::<Game>();
 
-    assert_eq!(game.run(), 4512);
+    assert_eq!(game.run(), Some(4512));
 }
 
 #[test]
 fn test_output_last() {
     let game = utils::test_input::<Game>();
-    assert_eq!(game.last_run(), 1924);
+    assert_eq!(game.last_run(), Some(1924));
+}
+
+
+#[test]
+fn test_rectangular() {
+    let mut text = String::from("3,1000,9,2,7,1\n\n");
+    text.push_str(" 1 2 1000\n 300 5 6\n\n");
+    text.push_str("7 8\n9 10\n3 12\n");
+
+    let (_, wide) = <Game as utils::Parsable>::parse(&text).unwrap();
+
+    assert_eq!(wide.boards[0], Board { rows: 2, cols: 3, numbers: vec![1, 2, 1000, 300, 5, 6] });
+    assert_eq!(wide.boards[1], Board { rows: 3, cols: 2, numbers: vec![7, 8, 9, 10, 3, 12] });
+    assert_eq!(wide.run(), Some(7 * (8 + 10 + 12)));
+    assert_eq!(wide.last_run(), Some(300 + 5 + 6));
+
+    // neither board is square
+    assert_eq!(wide.with_rule(WinRule::X).run(), None);
+}
+
+#[test]
+fn test_ragged_board() {
+    assert!(<Board as utils::Parsable>::parse("1 2 3\n4 5\n").is_err());
+}
+
+#[test]
+fn test_rules() {
+    // first board: 22 13 17 11 0 / 8 2 23 4 24 / 21 9 14 16 7 / 6 10 3 18 5 / 1 12 20 15 19
+    let game = utils::test_input::<Game>();
+    let with = |rule: WinRule, seq: Vec<u32>| Game { seq, ..game.clone().with_rule(rule) };
+
+    assert_eq!(with(WinRule::Corners, vec![22, 0, 1, 19]).run(), Some(19 * (300 - 22 - 1 - 19)));
+    assert_eq!(with(WinRule::Diagonals, vec![22, 2, 14, 18, 19]).run(), Some(19 * (300 - 75)));
+    assert_eq!(with(WinRule::X, vec![22, 2, 14, 18, 19, 0, 4, 10, 1]).run(), Some(300 - 90));
+    assert_eq!(with(WinRule::Masks(vec![Mask::parse("#\n#")]), vec![22, 8]).run(), Some(8 * (300 - 30)));
+    assert_eq!(with(WinRule::Masks(vec![Mask::parse("...")]), vec![22, 8]).run(), None);
+    assert_eq!(game.clone().with_rule(WinRule::Blackout).run(), Some(0));
+    assert_eq!(game.with_rule(WinRule::Lines).last_run(), Some(1924));
 }
 
+#[test]
+fn test_repeated_numbers() {
+    let text = "4,1,2,9,3\n\n1 1\n2 3\n\n3 9\n9 4\n";
+    let (_, game) = <Game as utils::Parsable>::parse(text).unwrap();
+
+    assert_eq!(game.index[&1], vec![(0, 0)]);
+    assert_eq!(game.index[&9], vec![(1, 1)]);
+    assert_eq!(game.run(), Some(2 * (1 + 3)));
+    assert_eq!(game.last_run(), Some(9 * (3 + 9)));
+}
+
+#[test]
+fn test_wide_numbers() {
+    let text = "100000,200000\n\n100000 200000\n300000 400000\n";
+    let (_, game) = <Game as utils::Parsable>::parse(text).unwrap();
+
+    assert_eq!(game.run(), Some(200000 * (300000 + 400000)));
+
+    // the score of a 5x5 board near u32::MAX no longer fits a u64
+    let big = u32::MAX - 24;
+    let seq = (big..=u32::MAX).collect::<Vec<_>>();
+    let board = Board { rows: 5, cols: 5, numbers: seq.clone() };
+    let last = seq[4] as u128 * seq[5..].iter().map(|n| *n as u128).sum::<u128>();
+
+    assert!(last > u64::MAX as u128);
+    assert_eq!(Game::new(seq, vec![board]).run(), Some(last));
+}
+
+#[cfg(test)]
+fn generate(boards: usize, range: u32) -> Game {
+    let mut rng = utils::rng::Rng::new(0x9e37_79b9_7f4a_7c15);
+
+    let mut shuffled = |n: u32| {
+        let mut v = (0..n).collect::<Vec<_>>();
+        for i in (1..v.len()).rev() {
+            v.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+        v
+    };
+
+    let seq = shuffled(range);
+    let boards = (0..boards)
+        .map(|_| Board { rows: 5, cols: 5, numbers: shuffled(range)[..25].to_vec() })
+        .collect();
+
+    Game::new(seq, boards)
+}
+
+// the first winning score the slow way: scan every cell of every board per
+// draw, then rescan every row and column
+#[cfg(test)]
+fn scan_run(game: &Game) -> Option<u128> {
+    let mut marked = vec![[false; 25]; game.boards.len()];
+
+    for s in &game.seq {
+        for (b, board) in game.boards.iter().enumerate() {
+            if let Some(i) = board.numbers.iter().position(|n| n == s) {
+                marked[b][i] = true;
+            }
+        }
+
+        for (b, m) in marked.iter().enumerate() {
+            let row = (0..5).any(|r| (0..5).all(|c| m[r * 5 + c]));
+            let col = (0..5).any(|c| (0..5).all(|r| m[r * 5 + c]));
+
+            if row || col {
+                let sum: u128 = (0..25).filter(|i| !m[*i]).map(|i| game.boards[b].numbers[i] as u128).sum();
+                return Some(*s as u128 * sum);
+            }
+        }
+    }
+
+    None
+}
+
+#[test]
+fn test_indexed_draws() {
+    let game = generate(200, 500);
+
+    assert_eq!(scan_run(&game), game.run());
+}
+
+// cargo test --release -- --ignored --nocapture bench_draws
+#[test]
+#[ignore]
+fn bench_draws() {
+    use std::time::Instant;
+
+    let game = generate(5000, 1000);
+
+    let start = Instant::now();
+    let scanned = scan_run(&game);
+    let scan_time = start.elapsed();
+
+    let start = Instant::now();
+    let indexed = game.run();
+    let index_time = start.elapsed();
+
+    assert_eq!(scanned, indexed);
+    println!("scan: {:?}, indexed: {:?}", scan_time, index_time);
+}
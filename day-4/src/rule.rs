@@ -0,0 +1,123 @@
+// A set of cells, given as (row, col), that wins once all of them are marked.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Mask {
+    cells: Vec<(usize, usize)>,
+}
+
+impl Mask {
+    // '#' marks a cell that is part of the pattern, anything else is ignored
+    pub fn parse(text: &str) -> Mask {
+        let mut cells = Vec::new();
+
+        for (r, line) in text.lines().enumerate() {
+            for (c, ch) in line.chars().enumerate() {
+                if ch == '#' {
+                    cells.push((r, c));
+                }
+            }
+        }
+
+        Mask { cells }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub enum WinRule {
+    #[default]
+    Lines,
+    Diagonals,
+    Corners,
+    Blackout,
+    X,
+    Masks(Vec<Mask>),
+    Any(Vec<WinRule>),
+}
+
+impl WinRule {
+    // Row-major cell indices of every winning pattern on a rows x cols board.
+    // Diagonal patterns only exist on square boards, and masks that are empty
+    // or do not fit on the board never win.
+    pub fn patterns(&self, rows: usize, cols: usize) -> Vec<Vec<usize>> {
+        let square = rows == cols;
+        let diag = (0..rows).map(|i| i * cols + i).collect::<Vec<_>>();
+        let anti = (0..rows).map(|i| i * cols + cols - 1 - i).collect::<Vec<_>>();
+
+        match self {
+            WinRule::Lines => {
+                let mut res = Vec::new();
+
+                for r in 0..rows {
+                    res.push((0..cols).map(|c| r * cols + c).collect());
+                }
+
+                for c in 0..cols {
+                    res.push((0..rows).map(|r| r * cols + c).collect());
+                }
+
+                res
+            }
+            WinRule::Diagonals if square => vec![diag, anti],
+            WinRule::Diagonals => vec![],
+            WinRule::Corners => vec![vec![0, cols - 1, (rows - 1) * cols, rows * cols - 1]],
+            WinRule::Blackout => vec![(0..rows * cols).collect()],
+            WinRule::X if square => {
+                let center = anti.into_iter().filter(|i| !diag.contains(i)).collect::<Vec<_>>();
+                vec![[diag, center].concat()]
+            }
+            WinRule::X => vec![],
+            WinRule::Masks(masks) => masks
+                .iter()
+                .filter(|m| !m.cells.is_empty() && m.cells.iter().all(|(r, c)| *r < rows && *c < cols))
+                .map(|m| m.cells.iter().map(|(r, c)| r * cols + c).collect())
+                .collect(),
+            WinRule::Any(rules) => rules.iter().flat_map(|r| r.patterns(rows, cols)).collect(),
+        }
+    }
+}
+
+impl std::str::FromStr for WinRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<WinRule, String> {
+        let rules = s
+            .split('+')
+            .map(|name| match name {
+                "lines" => Ok(WinRule::Lines),
+                "diagonals" => Ok(WinRule::Diagonals),
+                "corners" => Ok(WinRule::Corners),
+                "blackout" => Ok(WinRule::Blackout),
+                "x" => Ok(WinRule::X),
+                _ => Err(format!("unknown win rule {:?}", name)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(match rules.len() {
+            1 => rules.into_iter().next().unwrap(),
+            _ => WinRule::Any(rules),
+        })
+    }
+}
+
+#[test]
+fn test_patterns() {
+    assert_eq!(WinRule::Lines.patterns(2, 3), vec![vec![0, 1, 2], vec![3, 4, 5], vec![0, 3], vec![1, 4], vec![2, 5]]);
+    assert_eq!(WinRule::Diagonals.patterns(3, 3), vec![vec![0, 4, 8], vec![2, 4, 6]]);
+    assert_eq!(WinRule::Diagonals.patterns(2, 3).len(), 0);
+    assert_eq!("lines+diagonals".parse::<WinRule>().unwrap().patterns(3, 3).len(), 8);
+    assert_eq!(WinRule::Corners.patterns(3, 4), vec![vec![0, 3, 8, 11]]);
+    assert_eq!(WinRule::Blackout.patterns(2, 2), vec![vec![0, 1, 2, 3]]);
+    assert_eq!(WinRule::X.patterns(3, 3), vec![vec![0, 4, 8, 2, 6]]);
+}
+
+#[test]
+fn test_masks() {
+    let plus = Mask::parse(".#.\n###\n.#.");
+    let rule = WinRule::Masks(vec![plus]);
+
+    assert_eq!(rule.patterns(3, 3), vec![vec![1, 3, 4, 5, 7]]);
+    assert_eq!(rule.patterns(2, 2), Vec::<Vec<usize>>::new());
+    // an empty mask would be complete before any draw
+    assert_eq!(WinRule::Masks(vec![Mask::parse("..\n..")]).patterns(2, 2), Vec::<Vec<usize>>::new());
+    assert_eq!("corners+x".parse(), Ok(WinRule::Any(vec![WinRule::Corners, WinRule::X])));
+    assert!("zigzag".parse::<WinRule>().is_err());
+}
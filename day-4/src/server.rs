@@ -0,0 +1,481 @@
+use crate::{Board, Game, Mark};
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// One message per line.
+//
+//   client -> server: JOIN <name> | BINGO | QUIT
+//   server -> client: BOARD <rows> <cols> <numbers...> WINS <cells,...>...
+//                     | DRAW <index> <number> | WINNER <name> <score> | END
+//                     | ERR <reason>
+//
+// A board comes with the row-major cells of every pattern that wins on it,
+// so clients know the rule the server plays by.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Message {
+    Join(String),
+    Bingo,
+    Quit,
+    Board(Board, Vec<Vec<usize>>),
+    Draw(usize, u32),
+    Winner(String, u128),
+    End,
+    Err(String),
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Join(name) => write!(fmt, "JOIN {}", name),
+            Message::Bingo => write!(fmt, "BINGO"),
+            Message::Quit => write!(fmt, "QUIT"),
+            Message::Board(board, patterns) => {
+                write!(fmt, "BOARD {} {}", board.rows, board.cols)?;
+
+                for n in &board.numbers {
+                    write!(fmt, " {}", n)?;
+                }
+
+                write!(fmt, " WINS")?;
+
+                for p in patterns {
+                    let cells = p.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                    write!(fmt, " {}", cells.join(","))?;
+                }
+
+                Ok(())
+            }
+            Message::Draw(index, number) => write!(fmt, "DRAW {} {}", index, number),
+            Message::Winner(name, score) => write!(fmt, "WINNER {} {}", name, score),
+            Message::End => write!(fmt, "END"),
+            Message::Err(reason) => write!(fmt, "ERR {}", reason),
+        }
+    }
+}
+
+impl std::str::FromStr for Message {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Message, String> {
+        let malformed = || format!("malformed message {:?}", line);
+        let num = |s: &str| s.parse::<u32>().map_err(|_| malformed());
+
+        let (command, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let args = rest.split_whitespace().collect::<Vec<_>>();
+
+        match (command, &args[..]) {
+            ("JOIN", [name]) => Ok(Message::Join(name.to_string())),
+            ("BINGO", []) => Ok(Message::Bingo),
+            ("QUIT", []) => Ok(Message::Quit),
+            ("BOARD", [rows, cols, rest @ ..]) => {
+                let rows = num(rows)? as usize;
+                let cols = num(cols)? as usize;
+                let wins = rest.iter().position(|a| *a == "WINS").ok_or_else(malformed)?;
+
+                let numbers = rest[..wins].iter().map(|n| num(n)).collect::<Result<Vec<_>, _>>()?;
+                let patterns = rest[wins + 1..]
+                    .iter()
+                    .map(|p| p.split(',').map(|c| num(c).map(|c| c as usize)).collect::<Result<Vec<_>, _>>())
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if rows == 0 || cols == 0 || numbers.len() != rows * cols || patterns.iter().flatten().any(|c| *c >= numbers.len()) {
+                    return Err(malformed());
+                }
+
+                Ok(Message::Board(Board { rows, cols, numbers }, patterns))
+            }
+            ("DRAW", [index, number]) => Ok(Message::Draw(num(index)? as usize, num(number)?)),
+            ("WINNER", [name, score]) => Ok(Message::Winner(name.to_string(), score.parse().map_err(|_| malformed())?)),
+            ("END", []) => Ok(Message::End),
+            ("ERR", _) => Ok(Message::Err(rest.to_string())),
+            _ => Err(malformed()),
+        }
+    }
+}
+
+// the winning player and their score, if anyone called bingo
+pub type Outcome = Option<(String, u128)>;
+
+pub fn send(mut stream: &TcpStream, msg: &Message) -> io::Result<()> {
+    stream.write_all(format!("{}\n", msg).as_bytes())
+}
+
+// how long a board completed by the last number has to be claimed
+const CLAIM_WINDOW: Duration = Duration::from_secs(10);
+
+// Messages to a player go through its own writer thread, so a client that
+// stops reading never blocks anyone holding the state.
+struct Player {
+    name: String,
+    board: usize,
+    out: Sender<Message>,
+}
+
+#[derive(Default)]
+struct State {
+    drawn: Vec<u32>,
+    players: Vec<Player>,
+    dealt: usize,
+    winner: Outcome,
+    over: bool,
+}
+
+impl State {
+    fn broadcast(&self, msg: &Message) {
+        for p in &self.players {
+            // a player that went away is dropped once its handler notices
+            let _ = p.out.send(msg.clone());
+        }
+    }
+
+    fn join(&mut self, game: &Game, name: &str, out: &Sender<Message>) -> Result<(), String> {
+        if self.over {
+            return Err("game is over".to_string());
+        }
+
+        if self.players.iter().any(|p| p.name == name) {
+            return Err(format!("{} already joined", name));
+        }
+
+        let board = self.dealt;
+        if board >= game.boards.len() {
+            return Err("no boards left".to_string());
+        }
+
+        let dealt = &game.boards[board];
+        let _ = out.send(Message::Board(dealt.clone(), game.rule.patterns(dealt.rows, dealt.cols)));
+
+        // late joiners catch up on everything drawn so far
+        for (i, n) in self.drawn.iter().enumerate() {
+            let _ = out.send(Message::Draw(i, *n));
+        }
+
+        self.dealt += 1;
+        self.players.push(Player { name: name.to_string(), board, out: out.clone() });
+
+        Ok(())
+    }
+
+    fn claim(&mut self, game: &Game, name: &str) -> Result<(), String> {
+        if self.over {
+            return Err("game is over".to_string());
+        }
+
+        let board = self.players.iter().find(|p| p.name == name).map(|p| p.board).ok_or("join first")?;
+        let score = self.score(game, board).ok_or_else(|| format!("no bingo on board {}", board))?;
+
+        self.broadcast(&Message::Winner(name.to_string(), score));
+        self.winner = Some((name.to_string(), score));
+        self.finish();
+
+        Ok(())
+    }
+
+    // Scored on the draw that completed the board, however late the claim
+    // comes in.
+    fn score(&self, game: &Game, board: usize) -> Option<u128> {
+        let mut mark = Mark::new(&game.boards[board], &game.rule);
+
+        let completed = self.drawn.iter().find(|n| {
+            for (_, cell) in game.draws(**n).iter().filter(|(b, _)| *b == board) {
+                mark.mark_cell(*cell);
+            }
+
+            mark.done()
+        })?;
+
+        Some(*completed as u128 * mark.unmarked_sum() as u128)
+    }
+
+    // whether some player still sitting on a bingo has yet to claim it
+    fn unclaimed(&self, game: &Game) -> bool {
+        !self.over && self.players.iter().any(|p| self.score(game, p.board).is_some())
+    }
+
+    fn leave(&mut self, name: &str) {
+        self.players.retain(|p| p.name != name);
+    }
+
+    fn finish(&mut self) {
+        self.over = true;
+
+        // writers hang up once END is out
+        self.broadcast(&Message::End);
+    }
+}
+
+// Deals one board from the game to every player that joins and draws the
+// game's numbers once enough players are in. The first valid claim wins.
+pub struct Server {
+    game: Arc<Game>,
+    players: usize,
+    interval: Duration,
+    state: Arc<Mutex<State>>,
+}
+
+impl Server {
+    pub fn new(game: Game) -> Server {
+        Server {
+            game: Arc::new(game),
+            players: 1,
+            interval: Duration::from_secs(1),
+            state: Arc::default(),
+        }
+    }
+
+    // never more players than there are boards to deal
+    pub fn with_players(mut self, players: usize) -> Server {
+        self.players = players.min(self.game.boards.len()).max(1);
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Server {
+        self.interval = interval;
+        self
+    }
+
+    // Runs a single game on `listener`, returning the winner and score.
+    pub fn serve(self, listener: TcpListener) -> io::Result<Outcome> {
+        listener.set_nonblocking(true)?;
+
+        let accept = {
+            let game = self.game.clone();
+            let state = self.state.clone();
+
+            thread::spawn(move || loop {
+                if state.lock().unwrap().over {
+                    break;
+                }
+
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let game = game.clone();
+                        let state = state.clone();
+
+                        thread::spawn(move || handle(&game, &state, stream));
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(10)),
+                    Err(_) => break,
+                }
+            })
+        };
+
+        while self.state.lock().unwrap().players.len() < self.players {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        for (i, number) in self.game.seq.iter().enumerate() {
+            thread::sleep(self.interval);
+
+            let mut state = self.state.lock().unwrap();
+            if state.over {
+                break;
+            }
+
+            state.drawn.push(*number);
+            state.broadcast(&Message::Draw(i, *number));
+        }
+
+        // a board completed by the last number still gets its claim in
+        let deadline = Instant::now() + CLAIM_WINDOW;
+        while self.state.lock().unwrap().unclaimed(&self.game) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if !state.over {
+            state.finish();
+        }
+
+        let winner = state.winner.clone();
+        drop(state);
+
+        let _ = accept.join();
+
+        Ok(winner)
+    }
+}
+
+fn handle(game: &Game, state: &Mutex<State>, stream: TcpStream) {
+    if stream.set_nonblocking(false).is_err() {
+        return;
+    }
+
+    let (reader, writer) = match (stream.try_clone(), stream.try_clone()) {
+        (Ok(r), Ok(w)) => (BufReader::new(r), w),
+        _ => return,
+    };
+
+    let (out, outbox) = mpsc::channel::<Message>();
+
+    thread::spawn(move || {
+        for msg in outbox {
+            if send(&writer, &msg).is_err() {
+                break;
+            }
+
+            if msg == Message::End {
+                let _ = writer.shutdown(Shutdown::Both);
+                break;
+            }
+        }
+    });
+
+    let mut name = None;
+
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        let mut state = state.lock().unwrap();
+
+        let reply = match line.parse() {
+            Ok(Message::Join(_)) if name.is_some() => Err("already joined".to_string()),
+            Ok(Message::Join(n)) => state.join(game, &n, &out).map(|_| name = Some(n)),
+            Ok(Message::Bingo) => match &name {
+                Some(n) => state.claim(game, n),
+                None => Err("join first".to_string()),
+            },
+            Ok(Message::Quit) => break,
+            Ok(msg) => Err(format!("unexpected {}", msg)),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = reply {
+            let _ = out.send(Message::Err(e));
+        }
+    }
+
+    if let Some(name) = name {
+        state.lock().unwrap().leave(&name);
+    }
+}
+
+#[cfg(test)]
+fn start(game: Game, players: usize, interval: u64) -> (String, thread::JoinHandle<io::Result<Outcome>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let server = Server::new(game).with_players(players).with_interval(Duration::from_millis(interval));
+
+    (addr, thread::spawn(move || server.serve(listener)))
+}
+
+#[test]
+fn test_messages() {
+    let board = Board { rows: 2, cols: 3, numbers: vec![1, 2, 1000, 300, 5, 6] };
+    let messages = vec![
+        Message::Join("ada".to_string()),
+        Message::Bingo,
+        Message::Quit,
+        Message::Board(board.clone(), vec![vec![0, 1, 2], vec![3, 4, 5]]),
+        Message::Board(board, vec![]),
+        Message::Draw(3, 17),
+        Message::Winner("ada".to_string(), 4512),
+        Message::Winner("ada".to_string(), u32::MAX as u128 * 25 * u32::MAX as u128),
+        Message::End,
+        Message::Err("no boards left".to_string()),
+    ];
+
+    for msg in messages {
+        assert_eq!(msg.to_string().parse(), Ok(msg));
+    }
+
+    assert_eq!("BOARD 2 2 1 2 3 WINS".parse::<Message>(), Err("malformed message \"BOARD 2 2 1 2 3 WINS\"".to_string()));
+    assert!("BOARD 1 2 1 2 WINS 0,2".parse::<Message>().is_err());
+    assert!("BOARD 1 2 1 2".parse::<Message>().is_err());
+    assert!("JOIN".parse::<Message>().is_err());
+    assert!("DRAW x 1".parse::<Message>().is_err());
+}
+
+#[test]
+fn test_game() {
+    use crate::client::Client;
+
+    let game = utils::test_input::<Game>();
+    let score = |board| game.timeline().find(|e| e.board == board).unwrap().score;
+
+    // both players complete a line, and whoever claims first wins, but the
+    // score only depends on the board
+    let possible = [Some(("ada".to_string(), score(0))), Some(("bob".to_string(), score(1)))];
+    let (addr, server) = start(game.clone(), 2, 5);
+
+    let first = Client::connect(&addr, "ada").unwrap();
+    let second = Client::connect(&addr, "bob").unwrap();
+
+    let players = [first, second].map(|mut c| thread::spawn(move || c.run(true, io::sink())));
+    let outcome = server.join().unwrap().unwrap();
+
+    assert!(possible.contains(&outcome), "{:?}", outcome);
+
+    for p in players {
+        assert_eq!(p.join().unwrap().unwrap(), outcome);
+    }
+}
+
+#[test]
+fn test_sent_rule() {
+    use crate::client::Client;
+    use crate::WinRule;
+
+    // lines complete long before the blackout, so a client playing by lines
+    // would claim too early
+    let game = utils::test_input::<Game>().with_rule(WinRule::Blackout);
+    let expected = game.timeline().find(|e| e.board == 0).unwrap();
+    let (addr, server) = start(game, 1, 1);
+
+    let mut client = Client::connect(&addr, "ada").unwrap();
+    let mut out = Vec::new();
+    let outcome = client.run(true, &mut out).unwrap();
+
+    assert_eq!(outcome, Some(("ada".to_string(), expected.score)));
+    assert_eq!(server.join().unwrap().unwrap(), outcome);
+    assert!(!String::from_utf8(out).unwrap().contains("server:"));
+}
+
+#[test]
+fn test_claims() {
+    use crate::client::Client;
+
+    fn reply(lines: &mut impl Iterator<Item = String>) -> String {
+        lines.find(|l| !l.starts_with("DRAW")).unwrap()
+    }
+
+    let text = "1,2,3,4,5,6,7,8\n\n1 2\n3 4\n";
+    let (_, game) = <Game as utils::Parsable>::parse(text).unwrap();
+
+    // there is only one board to deal, so the game starts with one player
+    let (addr, server) = start(game, 3, 50);
+
+    let stream = TcpStream::connect(&addr).unwrap();
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines().map(|l| l.unwrap());
+
+    send(&stream, &Message::Bingo).unwrap();
+    assert_eq!(reply(&mut lines), "ERR join first");
+
+    send(&stream, &Message::Join("ada".to_string())).unwrap();
+    assert_eq!(reply(&mut lines), "BOARD 2 2 1 2 3 4 WINS 0,1 2,3 0,2 1,3");
+
+    // nothing drawn yet
+    send(&stream, &Message::Bingo).unwrap();
+    assert_eq!(reply(&mut lines), "ERR no bingo on board 0");
+
+    let late = Client::connect(&addr, "bob");
+    assert_eq!(late.err().map(|e| e.to_string()), Some("no boards left".to_string()));
+
+    // the top row completes on 2 leaving 3 + 4, claiming after 3 is drawn
+    // does not change that
+    lines.find(|l| l == "DRAW 2 3").unwrap();
+    send(&stream, &Message::Bingo).unwrap();
+    assert_eq!(reply(&mut lines), "WINNER ada 14");
+    assert_eq!(reply(&mut lines), "END");
+
+    assert_eq!(server.join().unwrap().unwrap(), Some(("ada".to_string(), 14)));
+}
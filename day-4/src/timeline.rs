@@ -0,0 +1,133 @@
+use crate::{Game, Mark};
+
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct WinEvent {
+    pub draw: usize,
+    pub number: u32,
+    pub board: usize,
+    // row-major cells of the completed pattern
+    pub line: Vec<usize>,
+    // a u32 times a sum of u32s
+    pub score: u128,
+}
+
+// Every win in draw order; boards completing on the same draw come out in
+// board order.
+pub struct Timeline<'a> {
+    game: &'a Game,
+    marks: Vec<Mark<'a>>,
+    draw: usize,
+    pending: VecDeque<WinEvent>,
+}
+
+impl<'a> Timeline<'a> {
+    pub fn new(game: &'a Game) -> Timeline<'a> {
+        Timeline {
+            game,
+            marks: game.marks(),
+            draw: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    // boards that have not won so far, which after the last event is every
+    // board that never wins
+    pub fn losers(&self) -> Vec<usize> {
+        (0..self.marks.len()).filter(|b| !self.marks[*b].done()).collect()
+    }
+}
+
+impl<'a> Iterator for Timeline<'a> {
+    type Item = WinEvent;
+
+    fn next(&mut self) -> Option<WinEvent> {
+        while self.pending.is_empty() {
+            let number = *self.game.seq.get(self.draw)?;
+
+            for (b, cell) in self.game.draws(number) {
+                let m = &mut self.marks[*b];
+
+                if m.done() {
+                    continue;
+                }
+
+                m.mark_cell(*cell);
+
+                if let Some(line) = m.winning_line() {
+                    self.pending.push_back(WinEvent {
+                        draw: self.draw,
+                        number,
+                        board: *b,
+                        line: line.to_vec(),
+                        score: number as u128 * m.unmarked_sum() as u128,
+                    });
+                }
+            }
+
+            self.draw += 1;
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+pub struct Leaderboard {
+    pub wins: Vec<WinEvent>,
+    pub losers: Vec<usize>,
+}
+
+impl Leaderboard {
+    pub fn new(game: &Game) -> Leaderboard {
+        let mut timeline = game.timeline();
+        let wins = timeline.by_ref().collect();
+
+        Leaderboard { wins, losers: timeline.losers() }
+    }
+}
+
+impl fmt::Display for Leaderboard {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "{:>4} {:>6} {:>6} {:>7} {:>8}", "rank", "board", "draw", "number", "score")?;
+
+        for (rank, w) in self.wins.iter().enumerate() {
+            writeln!(fmt, "{:>4} {:>6} {:>6} {:>7} {:>8}", rank + 1, w.board, w.draw, w.number, w.score)?;
+        }
+
+        for b in &self.losers {
+            writeln!(fmt, "{:>4} {:>6} {:>6} {:>7} {:>8}", "-", b, "-", "-", "-")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_timeline() {
+    let game = utils::test_input::<Game>();
+    let events = game.timeline().collect::<Vec<_>>();
+
+    assert_eq!(events.iter().map(|e| e.board).collect::<Vec<_>>(), vec![2, 0, 1]);
+    assert_eq!(
+        events[0],
+        WinEvent { draw: 11, number: 24, board: 2, line: vec![0, 1, 2, 3, 4], score: 4512 }
+    );
+    assert_eq!(events[2].number, 13);
+    assert_eq!(events[2].score, 1924);
+    assert_eq!(game.timeline().nth(1).map(|e| e.board), Some(0));
+}
+
+#[test]
+fn test_losers() {
+    let text = "1,2,3\n\n1 2\n5 6\n\n7 8\n9 10\n\n3 1\n4 4\n";
+    let (_, game) = <Game as utils::Parsable>::parse(text).unwrap();
+    let board = Leaderboard::new(&game);
+
+    assert_eq!(board.wins.len(), 2);
+    assert_eq!((board.wins[0].board, board.wins[0].draw, board.wins[0].line.clone()), (0, 1, vec![0, 1]));
+    assert_eq!((board.wins[1].board, board.wins[1].draw, board.wins[1].line.clone()), (2, 2, vec![0, 1]));
+    assert_eq!(board.losers, vec![1]);
+    assert!(board.to_string().lines().last().unwrap().trim_start().starts_with("-      1"));
+}
@@ -0,0 +1,356 @@
+use crate::{Bounds, Grid, Point, TILE};
+
+// Colours picked evenly along [0, 1], blended linearly in between.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ramp {
+    stops: Vec<[u8; 3]>,
+}
+
+impl Default for Ramp {
+    fn default() -> Ramp {
+        Ramp { stops: vec![[0, 0, 0], [255, 255, 255]] }
+    }
+}
+
+impl Ramp {
+    fn heat() -> Ramp {
+        Ramp { stops: vec![[0, 0, 0], [128, 0, 0], [255, 64, 0], [255, 200, 0], [255, 255, 255]] }
+    }
+
+    fn color(&self, t: f64) -> [u8; 3] {
+        if self.stops.len() == 1 {
+            return self.stops[0];
+        }
+
+        let pos = t.clamp(0.0, 1.0) * (self.stops.len() - 1) as f64;
+        let i = (pos.floor() as usize).min(self.stops.len() - 2);
+        let frac = pos - i as f64;
+        let (a, b) = (self.stops[i], self.stops[i + 1]);
+
+        [0, 1, 2].map(|c| (a[c] as f64 + (b[c] as f64 - a[c] as f64) * frac).round() as u8)
+    }
+
+    fn luma(&self, t: f64) -> u8 {
+        let [r, g, b] = self.color(t);
+        (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64).round() as u8
+    }
+}
+
+// "gray", "heat" or hex colours like "000000,ff0000,ffff00"
+impl std::str::FromStr for Ramp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Ramp, String> {
+        match s {
+            "gray" => Ok(Ramp::default()),
+            "heat" => Ok(Ramp::heat()),
+            _ => {
+                let hex = |c: &str| {
+                    let digits = c.strip_prefix('#').unwrap_or(c);
+                    let n = Some(digits)
+                        .filter(|d| d.len() == 6 && d.chars().all(|ch| ch.is_ascii_hexdigit()))
+                        .and_then(|d| u32::from_str_radix(d, 16).ok());
+
+                    n.map(|n| [(n >> 16) as u8, (n >> 8) as u8, n as u8]).ok_or_else(|| format!("bad colour {:?}", c))
+                };
+
+                let stops = s.split(',').map(hex).collect::<Result<Vec<_>, _>>()?;
+                Ok(Ramp { stops })
+            }
+        }
+    }
+}
+
+// "x0,y0,x1,y1", corners inclusive and in any order
+pub fn parse_region(s: &str) -> Result<Bounds, String> {
+    let nums = s.split(',').map(|n| n.trim().parse::<i64>()).collect::<Result<Vec<_>, _>>();
+
+    match nums.as_deref() {
+        Ok([x0, y0, x1, y1]) => Ok(Bounds { min: (*x0.min(x1), *y0.min(y1)), max: (*x0.max(x1), *y0.max(y1)) }),
+        _ => Err(format!("bad region {:?}, expected x0,y0,x1,y1", s)),
+    }
+}
+
+// Largest image drawn, beyond that a coarser scale or a crop is needed.
+const MAX_PIXELS: u128 = 1 << 24;
+
+// Overlap density over a region of a grid, with every scale x scale block of
+// cells shown as one pixel holding the block's highest count.
+pub struct Heatmap<'a> {
+    grid: &'a Grid,
+    region: Bounds,
+    scale: u64,
+    ramp: Ramp,
+}
+
+impl<'a> Heatmap<'a> {
+    // None for a grid without any lines
+    pub fn new(grid: &'a Grid) -> Option<Heatmap<'a>> {
+        let region = grid.bounds.clone()?;
+        Some(Heatmap { grid, region, scale: 1, ramp: Ramp::default() })
+    }
+
+    pub fn with_region(mut self, region: Bounds) -> Heatmap<'a> {
+        self.region = region;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: u64) -> Heatmap<'a> {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn with_ramp(mut self, ramp: Ramp) -> Heatmap<'a> {
+        self.ramp = ramp;
+        self
+    }
+
+    fn size(&self) -> Result<(usize, usize), String> {
+        let w = (self.region.max.0.abs_diff(self.region.min.0) as u128 + 1).div_ceil(self.scale as u128);
+        let h = (self.region.max.1.abs_diff(self.region.min.1) as u128 + 1).div_ceil(self.scale as u128);
+
+        if w * h > MAX_PIXELS {
+            return Err(format!("{} x {} pixels is too large, use --scale or --crop", w, h));
+        }
+
+        Ok((w as usize, h as usize))
+    }
+
+    // Row-major block maxima. Only tiles some line went through are read,
+    // everything else stays 0.
+    fn samples(&self) -> Result<Vec<u32>, String> {
+        let (w, h) = self.size()?;
+        let mut res = vec![0; w * h];
+
+        for ((tx, ty), tile) in &self.grid.tiles {
+            for (i, q) in tile.iter().enumerate() {
+                let x = tx * TILE + i as i64 % TILE;
+                let y = ty * TILE + i as i64 / TILE;
+
+                if *q == 0 || !self.region.contains(x, y) {
+                    continue;
+                }
+
+                let px = (x.abs_diff(self.region.min.0) / self.scale) as usize;
+                let py = (y.abs_diff(self.region.min.1) / self.scale) as usize;
+
+                res[py * w + px] = res[py * w + px].max(*q);
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn levels(&self) -> Result<Vec<f64>, String> {
+        let samples = self.samples()?;
+        let max = samples.iter().copied().max().unwrap_or(0).max(1) as f64;
+
+        Ok(samples.into_iter().map(|q| q as f64 / max).collect())
+    }
+
+    // binary greyscale, using the luminance of the ramp
+    pub fn pgm(&self) -> Result<Vec<u8>, String> {
+        let (w, h) = self.size()?;
+        let mut res = format!("P5\n{} {}\n255\n", w, h).into_bytes();
+
+        res.extend(self.levels()?.into_iter().map(|t| self.ramp.luma(t)));
+        Ok(res)
+    }
+
+    // 8-bit RGB, stored uncompressed
+    pub fn png(&self) -> Result<Vec<u8>, String> {
+        let (w, h) = self.size()?;
+        let levels = self.levels()?;
+
+        let mut raw = Vec::with_capacity(h * (3 * w + 1));
+        for row in levels.chunks(w) {
+            // no filter
+            raw.push(0);
+            raw.extend(row.iter().flat_map(|t| self.ramp.color(*t)));
+        }
+
+        let mut ihdr = Vec::new();
+        ihdr.extend((w as u32).to_be_bytes());
+        ihdr.extend((h as u32).to_be_bytes());
+        // bit depth, colour type RGB, compression, filter, interlace
+        ihdr.extend([8, 2, 0, 0, 0]);
+
+        let mut res = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+        chunk(&mut res, b"IHDR", &ihdr);
+        chunk(&mut res, b"IDAT", &zlib_stored(&raw));
+        chunk(&mut res, b"IEND", &[]);
+
+        Ok(res)
+    }
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    out.extend(kind);
+    out.extend(data);
+    out.extend(crc32(&[&kind[..], data].concat()).to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+// a zlib stream of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut res = vec![0x78, 0x01];
+    let blocks = data.chunks(0xffff).collect::<Vec<_>>();
+
+    if blocks.is_empty() {
+        res.extend([1, 0, 0, 0xff, 0xff]);
+    }
+
+    for (i, block) in blocks.iter().enumerate() {
+        let len = block.len() as u16;
+
+        res.push((i + 1 == blocks.len()) as u8);
+        res.extend(len.to_le_bytes());
+        res.extend((!len).to_le_bytes());
+        res.extend(*block);
+    }
+
+    res.extend(adler32(data).to_be_bytes());
+    res
+}
+
+impl Grid {
+    // One character per cell of `region`: '.' for nothing, the count up to 9
+    // and '+' beyond.
+    pub fn view(&self, region: &Bounds) -> String {
+        let mut res = String::new();
+
+        for y in region.min.1..=region.max.1 {
+            for x in region.min.0..=region.max.0 {
+                res.push(match self.get(&Point { x, y, z: 0 }) {
+                    0 => '.',
+                    q @ 1..=9 => char::from_digit(q, 10).unwrap(),
+                    _ => '+',
+                });
+            }
+
+            res.push('\n');
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+fn sample_grid() -> Grid {
+    let mut grid = Grid::default();
+    for line in utils::test_input_vec::<crate::Line>() {
+        grid.imprint(&line);
+    }
+
+    grid
+}
+
+#[test]
+fn test_ramp() {
+    let heat: Ramp = "000000,ff0000,ffffff".parse().unwrap();
+
+    assert_eq!(heat.color(0.0), [0, 0, 0]);
+    assert_eq!(heat.color(0.25), [128, 0, 0]);
+    assert_eq!(heat.color(0.5), [255, 0, 0]);
+    assert_eq!(heat.color(1.0), [255, 255, 255]);
+    assert_eq!(Ramp::default().luma(0.5), 128);
+    assert!("ff00".parse::<Ramp>().is_err());
+    assert!("#ff000".parse::<Ramp>().is_err());
+    assert!("ff000000".parse::<Ramp>().is_err());
+    assert!("+fffff".parse::<Ramp>().is_err());
+    assert_eq!("#ff8000".parse::<Ramp>().map(|r| r.color(0.0)), Ok([255, 128, 0]));
+    assert_eq!(parse_region("3,4,-1,0"), Ok(Bounds { min: (-1, 0), max: (3, 4) }));
+}
+
+#[test]
+fn test_pgm() {
+    let grid = sample_grid();
+    let pgm = Heatmap::new(&grid).unwrap().with_scale(4).pgm().unwrap();
+
+    // the sample spans 0..=9 both ways, so 3 x 3 blocks
+    let header = "P5\n3 3\n255\n";
+    assert_eq!(&pgm[..header.len()], header.as_bytes());
+
+    // the only 3 is in the middle block, the right column and bottom row
+    // blocks are 2 wide
+    assert_eq!(&pgm[header.len()..], &[170, 170, 85, 170, 255, 85, 170, 85, 85]);
+}
+
+#[test]
+fn test_png() {
+    let grid = sample_grid();
+    let region = parse_region("0,1,2,2").unwrap();
+    let png = Heatmap::new(&grid).unwrap().with_region(region).with_ramp(Ramp::heat()).png().unwrap();
+
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&png[8..16], b"\x00\x00\x00\x0dIHDR");
+    assert_eq!(&png[16..29], &[0, 0, 0, 3, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+    assert_eq!(&png[png.len() - 12..], b"\x00\x00\x00\x00IEND\xae\x42\x60\x82");
+
+    // single stored block holding both filtered rows
+    let idat = &png[8 + 25 + 8..];
+    let (len, data) = (u16::from_le_bytes([idat[3], idat[4]]) as usize, &idat[7..]);
+    assert_eq!(len, 2 * (1 + 3 * 3));
+
+    // ".11" over "..2", scaled against the 2
+    let half = Ramp::heat().color(0.5);
+    assert_eq!(&data[..10], &[[0].as_slice(), &[0, 0, 0], &half, &half].concat()[..]);
+    assert_eq!(&data[10..20], &[[0].as_slice(), &[0, 0, 0], &[0, 0, 0], &[255, 255, 255]].concat()[..]);
+    assert_eq!(adler32(&data[..len]).to_be_bytes(), data[len..len + 4]);
+}
+
+#[test]
+fn test_far_away() {
+    use crate::Line;
+
+    let mut grid = Grid::default();
+    grid.imprint(&Line { start: Point { x: 0, y: 0, z: 0 }, end: Point { x: 3, y: 0, z: 0 } });
+    grid.imprint(&Line { start: Point { x: 0, y: 0, z: 0 }, end: Point { x: 0, y: 0, z: 0 } });
+    grid.imprint(&Line { start: Point { x: 1 << 40, y: 1 << 40, z: 0 }, end: Point { x: 1 << 40, y: 1 << 40, z: 0 } });
+
+    let map = Heatmap::new(&grid).unwrap();
+    assert!(map.pgm().unwrap_err().contains("too large"));
+
+    // one pixel per 2^38 cells, found without visiting the empty ones
+    let coarse = map.with_scale(1 << 38).pgm().unwrap();
+    let header = "P5\n5 5\n255\n";
+    assert_eq!(&coarse[..header.len()], header.as_bytes());
+    assert_eq!(coarse[header.len()], 255);
+    assert_eq!(coarse[header.len() + 24], 128);
+
+    let crop = Heatmap::new(&grid).unwrap().with_region(parse_region("0,0,3,0").unwrap()).pgm().unwrap();
+    assert_eq!(&crop[crop.len() - 4..], &[255, 128, 128, 128]);
+}
+
+#[test]
+fn test_view() {
+    let grid = sample_grid();
+
+    assert_eq!(grid.view(&parse_region("0,0,3,2").unwrap()), "1.1.\n.111\n..2.\n");
+    assert_eq!(grid.to_string().lines().nth(4), Some(".112313211"));
+}
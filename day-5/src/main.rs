@@ -1,34 +1,94 @@
 use nom::{
-    character::complete::digit1,
+    character::complete::i64 as parse_i64,
     bytes::complete::tag,
-    combinator::map_res,
+    combinator::opt,
+    sequence::preceded,
     IResult
 };
 
+mod heatmap;
+mod sweep;
+
+use heatmap::{Heatmap, Ramp};
+use std::collections::HashMap;
+
 fn main() {
     let lines = utils::input_vec::<Line>();
-    let mut grid = Grid::<1000>::default();
-    for line in lines {
-        if line.snapped() {
-            grid.imprint(&line);
-        }
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+    let value = |name: &str| args.iter().position(|arg| arg == name).map(|i| args.get(i + 1).unwrap_or_else(|| fail(usage())));
+
+    if flag("--analytic") {
+        let snapped = lines.iter().filter(|l| l.snapped()).cloned().collect::<Vec<_>>();
+        let count = |lines: &[Line]| {
+            sweep::overlaps(lines)
+                .unwrap_or_else(|| fail("--analytic only counts straight and 45 degree lines in the z = 0 plane"))
+        };
+
+        println!("hazard snapped square count: {}", count(&snapped));
+        println!("hazard square count: {}", count(&lines));
+        return;
     }
 
-    println!("hazard snapped square count: {}", grid.count(|d| d>= 2));
+    let (snapped, diagonal): (Vec<_>, Vec<_>) = lines.iter().partition(|l| l.snapped());
 
-    let lines = utils::input_vec::<Line>();
-    let mut grid = Grid::<1000>::default();
-    for line in lines {
-        grid.imprint(&line);
+    let raster = if flag("--lattice") { Raster::Lattice } else { Raster::Bresenham };
+
+    let mut grid = Grid::sized(&lines).with_raster(raster);
+    for line in snapped {
+        grid.imprint(line);
+    }
+
+    println!("hazard snapped square count: {}", grid.count(|d| d >= 2));
+
+    for line in diagonal {
+        grid.imprint(line);
+    }
+
+    println!("hazard square count: {}", grid.count(|d| d >= 2));
+
+    if flag("--3d") {
+        println!("hazard cube count: {}", overlaps_3d(&lines, raster));
     }
 
-    println!("hazard square count: {}", grid.count(|d| d>= 2));
+    let region = value("--crop").map(|r| heatmap::parse_region(r).unwrap_or_else(|e| fail(e)));
+
+    if let Some(region) = &region {
+        print!("{}", grid.view(region));
+    }
+
+    if let Some(file) = value("--heatmap") {
+        let ramp = value("--ramp").map_or(Ok(Ramp::default()), |r| r.parse()).unwrap_or_else(|e| fail(e));
+        let scale = value("--scale").map_or(Ok(1), |s| s.parse()).unwrap_or_else(|_| fail(usage()));
+
+        let mut map = Heatmap::new(&grid).unwrap_or_else(|| fail("no lines to draw")).with_scale(scale).with_ramp(ramp);
+        if let Some(region) = region {
+            map = map.with_region(region);
+        }
+
+        let image = if file.ends_with(".png") { map.png() } else { map.pgm() };
+        std::fs::write(file, image.unwrap_or_else(|e| fail(e))).unwrap_or_else(|e| fail(format!("{}: {}", file, e)));
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+fn fail(e: impl std::fmt::Display) -> ! {
+    eprintln!("{}", e);
+    std::process::exit(1);
+}
+
+fn usage() -> String {
+    "usage: day-5 [--analytic] [--lattice] [--3d] [--crop x0,y0,x1,y1] \
+     [--heatmap file.pgm|file.png [--ramp gray|heat|rrggbb,...] [--scale n]]"
+        .to_string()
+}
+
+// Lines given as `x,y -> x,y` lie in the z = 0 plane.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Point {
-    x: u32,
-    y: u32,
+    x: i64,
+    y: i64,
+    z: i64,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -37,54 +97,113 @@ pub struct Line {
     end: Point,
 }
 
+// How a line is turned into grid points.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Raster {
+    // the closest point on every row or column the line crosses
+    #[default]
+    Bresenham,
+    // only the integer points exactly on the line
+    Lattice,
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 impl Line {
+    fn deltas(&self) -> [u64; 3] {
+        [
+            self.start.x.abs_diff(self.end.x),
+            self.start.y.abs_diff(self.end.y),
+            self.start.z.abs_diff(self.end.z),
+        ]
+    }
+
+    fn is_planar(&self) -> bool {
+        self.start.z == self.end.z
+    }
+
+    // The classifications below only hold for lines within one z plane.
+    fn is_horizontal(&self) -> bool {
+        matches!(self.deltas(), [_, 0, 0])
+    }
+
+    fn is_vertical(&self) -> bool {
+        matches!(self.deltas(), [0, _, 0])
+    }
+
+    // at exactly 45 degrees, either way
+    fn is_diagonal(&self) -> bool {
+        matches!(self.deltas(), [dx, dy, 0] if dx != 0 && dx == dy)
+    }
+
     fn snapped(&self) -> bool {
-        self.start.x == self.end.x || self.start.y == self.end.y
+        self.is_horizontal() || self.is_vertical()
     }
 
-    fn iter(&self) -> LineIter {
-        let Point { x: sx, y: sy } = self.start;
-        let Point { x: ex, y: ey } = self.end;
+    // rise over run in the xy plane, in lowest terms with a positive run,
+    // None when vertical
+    fn slope(&self) -> Option<(i128, i128)> {
+        let run = self.end.x as i128 - self.start.x as i128;
+        let rise = self.end.y as i128 - self.start.y as i128;
 
-        let dx = if sx < ex {
-            Mono::Inc
-        } else if sx > ex {
-            Mono::Dec
-        } else {
-            Mono::Stag
-        };
+        if run == 0 {
+            return None;
+        }
 
-        let dy = if sy < ey {
-            Mono::Inc
-        } else if sy > ey {
-            Mono::Dec
-        } else {
-            Mono::Stag
-        };
+        let g = gcd(rise.unsigned_abs() as u64, run.unsigned_abs() as u64) as i128;
+        Some((rise.signum() * run.signum() * rise.abs() / g, run.abs() / g))
+    }
 
+    fn raster(&self, raster: Raster) -> LineIter {
+        let Point { x: sx, y: sy, z: sz } = self.start;
+        let Point { x: ex, y: ey, z: ez } = self.end;
 
-        let lenx = (sx as i32 - ex as i32).abs() as u32;
-        let leny = (sy as i32 - ey as i32).abs() as u32;
-        let len = lenx.max(leny) + 1;
+        let dirs = [Mono::between(sx, ex), Mono::between(sy, ey), Mono::between(sz, ez)];
+        let deltas = self.deltas();
+        let major = deltas.into_iter().max().unwrap();
+        let g = deltas.into_iter().fold(0, gcd);
+
+        let (step, len) = match raster {
+            Raster::Bresenham => (None, major as u128 + 1),
+            Raster::Lattice => (Some(deltas.map(|d| d / g.max(1))), g as u128 + 1),
+        };
 
         LineIter {
-            x: sx, y: sy,
-            dx, dy,
-            len
+            pos: [sx, sy, sz],
+            dirs,
+            deltas,
+            major,
+            err: deltas.map(|d| 2 * d as i128 - major as i128),
+            step,
+            len,
         }
     }
 }
 
+// Walks the axis with the largest delta one unit at a time, moving every
+// other axis whenever its error term says the line has crossed into the next
+// cell.
 #[derive(Debug)]
 struct LineIter {
-    x: u32,
-    y: u32,
-    dx: Mono,
-    dy: Mono,
-    len: u32,
+    pos: [i64; 3],
+    dirs: [Mono; 3],
+    deltas: [u64; 3],
+    major: u64,
+    // scaled by 2 * major so that the decision needs no division
+    err: [i128; 3],
+    // fixed step between lattice points, None when rasterizing
+    step: Option<[u64; 3]>,
+    // a line across the whole i64 range has 2^64 points
+    len: u128,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum Mono {
     Stag,
     Inc,
@@ -92,11 +211,22 @@ enum Mono {
 }
 
 impl Mono {
-    fn apply(&self, val: u32) -> u32 {
+    fn between(from: i64, to: i64) -> Mono {
+        if from < to {
+            Mono::Inc
+        } else if from > to {
+            Mono::Dec
+        } else {
+            Mono::Stag
+        }
+    }
+
+    // wraps only on the step past the last point, which is never returned
+    fn apply(&self, val: i64, by: u64) -> i64 {
         match self {
             Mono::Stag => val,
-            Mono::Inc => val + 1,
-            Mono::Dec => val.saturating_sub(1),
+            Mono::Inc => val.wrapping_add_unsigned(by),
+            Mono::Dec => val.wrapping_sub_unsigned(by),
         }
     }
 }
@@ -108,48 +238,176 @@ impl Iterator for LineIter {
             return None;
         }
 
-        let p = Point { x: self.x, y: self.y };
-
-        self.x = self.dx.apply(self.x);
-        self.y = self.dy.apply(self.y);
+        let [x, y, z] = self.pos;
         self.len -= 1;
 
-        Some(p)
+        for axis in 0..3 {
+            let by = match self.step {
+                Some(step) => step[axis],
+                None if self.deltas[axis] == self.major => 1,
+                None if self.err[axis] > 0 => {
+                    self.err[axis] += 2 * self.deltas[axis] as i128 - 2 * self.major as i128;
+                    1
+                }
+                None => {
+                    self.err[axis] += 2 * self.deltas[axis] as i128;
+                    0
+                }
+            };
+
+            self.pos[axis] = self.dirs[axis].apply(self.pos[axis], by);
+        }
+
+        Some(Point { x, y, z })
     }
 }
 
+const TILE: i64 = 32;
+
+// Inclusive box around every point a grid covers, as (x, y).
 #[derive(Debug, PartialEq, Clone)]
-pub struct Grid<const W: usize> {
-    quant: [[u8; W]; W],
+pub struct Bounds {
+    min: (i64, i64),
+    max: (i64, i64),
 }
 
-impl<const W: usize> Default for Grid<W> {
-    fn default() -> Grid<W> {
-        Grid { quant: [[0u8; W]; W] }
+impl Bounds {
+    fn of(lines: &[Line]) -> Option<Bounds> {
+        let mut points = lines.iter().flat_map(|l| [&l.start, &l.end]);
+        let first = points.next()?;
+        let mut bounds = Bounds { min: (first.x, first.y), max: (first.x, first.y) };
+
+        for p in points {
+            bounds.extend(p);
+        }
+
+        Some(bounds)
+    }
+
+    fn extend(&mut self, p: &Point) {
+        self.min = (self.min.0.min(p.x), self.min.1.min(p.y));
+        self.max = (self.max.0.max(p.x), self.max.1.max(p.y));
     }
+
+    fn contains(&self, x: i64, y: i64) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+
+    fn area(&self) -> u128 {
+        (self.max.0.abs_diff(self.min.0) as u128 + 1) * (self.max.1.abs_diff(self.min.1) as u128 + 1)
+    }
+}
+
+fn tile_of(p: &Point) -> (i64, i64) {
+    (p.x.div_euclid(TILE), p.y.div_euclid(TILE))
+}
+
+fn cell_of(p: &Point) -> usize {
+    (p.y.rem_euclid(TILE) * TILE + p.x.rem_euclid(TILE)) as usize
+}
+
+// Overlap counts in the xy plane, stored in TILE x TILE blocks that are only allocated once a
+// line crosses them, so the map costs memory in proportion to the lines
+// rather than to the coordinates.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Grid {
+    tiles: HashMap<(i64, i64), Box<[u32]>>,
+    bounds: Option<Bounds>,
+    raster: Raster,
 }
 
-impl<const W: usize> Grid<W> {
+impl Grid {
+    // a grid covering the bounding box of `lines`, before any are imprinted
+    fn sized(lines: &[Line]) -> Grid {
+        let planar = lines.iter().filter(|l| l.is_planar()).cloned().collect::<Vec<_>>();
+        Grid { bounds: Bounds::of(&planar), ..Grid::default() }
+    }
+
+    fn with_raster(mut self, raster: Raster) -> Grid {
+        self.raster = raster;
+        self
+    }
+
+    // Lines within one z plane are projected onto xy. A line climbing along
+    // z would cover some cells several times and overlap itself, so those
+    // are left to overlaps_3d.
     fn imprint(&mut self, line: &Line) {
-        for p in line.iter() {
-            self.quant[p.x as usize][p.y as usize] += 1;
+        if !line.is_planar() {
+            return;
+        }
+
+        match &mut self.bounds {
+            Some(bounds) => {
+                bounds.extend(&line.start);
+                bounds.extend(&line.end);
+            }
+            None => self.bounds = Bounds::of(std::slice::from_ref(line)),
+        }
+
+        for p in line.raster(self.raster) {
+            let tile = self
+                .tiles
+                .entry(tile_of(&p))
+                .or_insert_with(|| vec![0; (TILE * TILE) as usize].into_boxed_slice());
+
+            let cell = &mut tile[cell_of(&p)];
+            *cell = cell.saturating_add(1);
         }
     }
 
-    fn count(&self, pred: impl Fn(u8) -> bool) -> u32 {
+    fn get(&self, p: &Point) -> u32 {
+        self.tiles
+            .get(&tile_of(p))
+            .map_or(0, |tile| tile[cell_of(p)])
+    }
+
+    // Cells within the bounding box matching `pred`; cells no line crosses
+    // count as 0.
+    fn count(&self, pred: impl Fn(u32) -> bool) -> u128 {
+        let bounds = match &self.bounds {
+            Some(bounds) => bounds,
+            None => return 0,
+        };
+
         let mut res = 0;
-        for x in 0..W {
-            for y in 0..W {
-                if pred(self.quant[x][y]) {
-                    res += 1;
+        let mut stored = 0;
+
+        for ((tx, ty), tile) in &self.tiles {
+            for (i, quant) in tile.iter().enumerate() {
+                let x = tx * TILE + i as i64 % TILE;
+                let y = ty * TILE + i as i64 / TILE;
+
+                if bounds.contains(x, y) {
+                    stored += 1;
+
+                    if pred(*quant) {
+                        res += 1;
+                    }
                 }
             }
         }
 
+        if pred(0) {
+            res += bounds.area() - stored;
+        }
+
         res
     }
 }
 
+// Points in space covered by at least two lines.
+fn overlaps_3d(lines: &[Line], raster: Raster) -> usize {
+    let mut counts: HashMap<Point, u32> = HashMap::new();
+
+    for line in lines {
+        for p in line.raster(raster) {
+            *counts.entry(p).or_default() += 1;
+        }
+    }
+
+    counts.values().filter(|c| **c >= 2).count()
+}
+
 impl utils::Parsable for Line {
     fn parse(input: &str) -> IResult<&str, Line> {
         let (input, start) = Point::parse(input)?;
@@ -162,27 +420,21 @@ impl utils::Parsable for Line {
 
 impl utils::Parsable for Point {
     fn parse(input: &str) -> IResult<&str, Point> {
-        let (input, x) = map_res(digit1, |s: &str| s.parse::<u32>())(input)?;
+        let (input, x) = parse_i64(input)?;
         let (input, _) = tag(",")(input)?;
-        let (input, y) = map_res(digit1, |s: &str| s.parse::<u32>())(input)?;
+        let (input, y) = parse_i64(input)?;
+        let (input, z) = opt(preceded(tag(","), parse_i64))(input)?;
 
-        Ok((input, Point { x, y }))
+        Ok((input, Point { x, y, z: z.unwrap_or(0) }))
     }
 }
 
-impl<const W: usize> std::fmt::Display for Grid<W> {
+impl std::fmt::Display for Grid {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        for y in 0..W {
-            for x in 0..W {
-                match self.quant[x][y] {
-                    0 => write!(fmt, ".")?,
-                    q => write!(fmt, "{}", q)?,
-               }
-            }
-            write!(fmt, "\n")?;
+        match &self.bounds {
+            Some(bounds) => write!(fmt, "{}", self.view(bounds)),
+            None => Ok(()),
         }
-
-        Ok(())
     }
 }
 
@@ -193,44 +445,44 @@ fn test_input() {
         lines,
         vec![
             Line {
-                start: Point { x: 0, y: 9 },
-                end: Point { x: 5, y: 9 }
+                start: Point { x: 0, y: 9, z: 0 },
+                end: Point { x: 5, y: 9, z: 0 }
             },
             Line {
-                start: Point { x: 8, y: 0 },
-                end: Point { x: 0, y: 8 }
+                start: Point { x: 8, y: 0, z: 0 },
+                end: Point { x: 0, y: 8, z: 0 }
             },
             Line {
-                start: Point { x: 9, y: 4 },
-                end: Point { x: 3, y: 4 }
+                start: Point { x: 9, y: 4, z: 0 },
+                end: Point { x: 3, y: 4, z: 0 }
             },
             Line {
-                start: Point { x: 2, y: 2 },
-                end: Point { x: 2, y: 1 }
+                start: Point { x: 2, y: 2, z: 0 },
+                end: Point { x: 2, y: 1, z: 0 }
             },
             Line {
-                start: Point { x: 7, y: 0 },
-                end: Point { x: 7, y: 4 }
+                start: Point { x: 7, y: 0, z: 0 },
+                end: Point { x: 7, y: 4, z: 0 }
             },
             Line {
-                start: Point { x: 6, y: 4 },
-                end: Point { x: 2, y: 0 }
+                start: Point { x: 6, y: 4, z: 0 },
+                end: Point { x: 2, y: 0, z: 0 }
             },
             Line {
-                start: Point { x: 0, y: 9 },
-                end: Point { x: 2, y: 9 }
+                start: Point { x: 0, y: 9, z: 0 },
+                end: Point { x: 2, y: 9, z: 0 }
             },
             Line {
-                start: Point { x: 3, y: 4 },
-                end: Point { x: 1, y: 4 }
+                start: Point { x: 3, y: 4, z: 0 },
+                end: Point { x: 1, y: 4, z: 0 }
             },
             Line {
-                start: Point { x: 0, y: 0 },
-                end: Point { x: 8, y: 8 }
+                start: Point { x: 0, y: 0, z: 0 },
+                end: Point { x: 8, y: 8, z: 0 }
             },
             Line {
-                start: Point { x: 5, y: 5 },
-                end: Point { x: 8, y: 2 }
+                start: Point { x: 5, y: 5, z: 0 },
+                end: Point { x: 8, y: 2, z: 0 }
             },
         ]
     );
@@ -239,7 +491,7 @@ fn test_input() {
 #[test]
 fn test_depth() {
     let lines = utils::test_input_vec::<Line>();
-    let mut grid = Grid::<10>::default();
+    let mut grid = Grid::default();
     for line in lines {
         if line.snapped() {
             grid.imprint(&line);
@@ -256,7 +508,7 @@ fn test_depth() {
 #[test]
 fn test_depth2() {
     let lines = utils::test_input_vec::<Line>();
-    let mut grid = Grid::<10>::default();
+    let mut grid = Grid::default();
     for line in lines {
         grid.imprint(&line);
         println!("applying: {:?}", line);
@@ -267,3 +519,131 @@ fn test_depth2() {
 
     assert_eq!(grid.count(|d| d >= 2), 12);
 }
+
+#[test]
+fn test_large_coordinates() {
+    let far = 10_000_000_000;
+    let lines = vec![
+        Line { start: Point { x: far, y: 0, z: 0 }, end: Point { x: far, y: 4, z: 0 } },
+        Line { start: Point { x: far - 2, y: 2, z: 0 }, end: Point { x: far + 2, y: 2, z: 0 } },
+        Line { start: Point { x: far - 2, y: 0, z: 0 }, end: Point { x: far + 2, y: 4, z: 0 } },
+    ];
+
+    let mut grid = Grid::sized(&lines);
+    for line in &lines {
+        grid.imprint(line);
+    }
+
+    assert_eq!(grid.get(&Point { x: far, y: 2, z: 0 }), 3);
+    assert_eq!(grid.count(|d| d >= 2), 1);
+    assert_eq!(grid.count(|d| d == 1), 12);
+    assert_eq!(grid.count(|d| d == 0), 25 - 13);
+    assert_eq!(grid.tiles.len(), 2);
+}
+
+#[test]
+fn test_many_overlaps() {
+    let line = Line { start: Point { x: 0, y: 0, z: 0 }, end: Point { x: 3, y: 3, z: 0 } };
+
+    let mut grid = Grid::default();
+    for _ in 0..300 {
+        grid.imprint(&line);
+    }
+
+    assert_eq!(grid.get(&Point { x: 2, y: 2, z: 0 }), 300);
+    assert_eq!(grid.count(|d| d >= 256), 4);
+    assert_eq!(grid.count(|d| d == 0), 12);
+    assert_eq!(Grid::sized(&[line]).count(|d| d == 0), 16);
+}
+
+#[test]
+fn test_classify() {
+    let line = |sx, sy, ex, ey| Line { start: Point { x: sx, y: sy, z: 0 }, end: Point { x: ex, y: ey, z: 0 } };
+
+    assert!(line(0, 3, 7, 3).is_horizontal());
+    assert!(line(2, 0, 2, 9).is_vertical());
+    assert!(line(8, 0, 0, 8).is_diagonal());
+    assert!(!line(0, 0, 6, 2).is_diagonal());
+    assert!(!line(4, 4, 4, 4).is_diagonal());
+
+    assert_eq!(line(0, 0, 6, 2).slope(), Some((1, 3)));
+    assert_eq!(line(6, 2, 0, 0).slope(), Some((1, 3)));
+    assert_eq!(line(0, 9, 4, 3).slope(), Some((-3, 2)));
+    assert_eq!(line(0, 3, 7, 3).slope(), Some((0, 1)));
+    assert_eq!(line(2, 0, 2, 9).slope(), None);
+}
+
+#[test]
+fn test_raster() {
+    let line = |sx, sy, ex, ey| Line { start: Point { x: sx, y: sy, z: 0 }, end: Point { x: ex, y: ey, z: 0 } };
+    let points = |l: Line, r| l.raster(r).map(|p| (p.x, p.y)).collect::<Vec<_>>();
+
+    assert_eq!(
+        points(line(0, 0, 6, 2), Raster::Bresenham),
+        vec![(0, 0), (1, 0), (2, 1), (3, 1), (4, 1), (5, 2), (6, 2)]
+    );
+    assert_eq!(points(line(6, 2, 0, 0), Raster::Lattice), vec![(6, 2), (3, 1), (0, 0)]);
+    assert_eq!(points(line(0, 0, 5, 2), Raster::Lattice), vec![(0, 0), (5, 2)]);
+    assert_eq!(points(line(3, 3, 3, 3), Raster::Lattice), vec![(3, 3)]);
+
+    // straight and 45 degree lines come out the same either way
+    for l in utils::test_input_vec::<Line>() {
+        assert_eq!(points(l.clone(), Raster::Bresenham), points(l.clone(), Raster::Lattice));
+    }
+
+    // every step moves at most one cell and stays within half a cell of the line
+    for (ex, ey) in [(13, 5), (2, 11), (9, 9), (0, 7), (17, 1)] {
+        let steep = points(line(0, 0, ex, -ey), Raster::Bresenham);
+
+        assert_eq!(steep.len() as i64, ex.max(ey) + 1);
+        assert_eq!(steep.last(), Some(&(ex, -ey)));
+
+        for w in steep.windows(2) {
+            assert!(w[0].0.abs_diff(w[1].0) <= 1 && w[0].1.abs_diff(w[1].1) <= 1);
+        }
+
+        for (x, y) in steep {
+            // |y - line(x)| <= 1/2 along the major axis
+            let off = if ex >= ey { -y * ex - x * ey } else { x * ey + y * ex };
+            assert!(2 * off.abs() <= ex.max(ey));
+        }
+    }
+}
+
+#[test]
+fn test_signed_3d() {
+    let (_, line) = <Line as utils::Parsable>::parse("-3,4,-1 -> 1,0,3").unwrap();
+    assert_eq!(line, Line { start: Point { x: -3, y: 4, z: -1 }, end: Point { x: 1, y: 0, z: 3 } });
+
+    let (_, flat) = <Line as utils::Parsable>::parse("-2,-2 -> 2,-2").unwrap();
+    assert_eq!(flat.start, Point { x: -2, y: -2, z: 0 });
+    assert!(flat.is_horizontal());
+    assert!(!line.is_diagonal());
+
+    let lines = vec![
+        line,
+        // crosses the first line at (-1, 2, 1)
+        Line { start: Point { x: -1, y: 2, z: -5 }, end: Point { x: -1, y: 2, z: 5 } },
+        // the same xy projection as the first line, a plane below
+        Line { start: Point { x: -3, y: 4, z: -2 }, end: Point { x: 1, y: 0, z: 2 } },
+        // flat, through (-1, 2, 0) where the line along z meets the one above
+        Line { start: Point { x: -1, y: 0, z: 0 }, end: Point { x: -1, y: 4, z: 0 } },
+        // flat, crossing the one above only when projected
+        Line { start: Point { x: -3, y: 2, z: 7 }, end: Point { x: 1, y: 2, z: 7 } },
+    ];
+
+    assert_eq!(overlaps_3d(&lines, Raster::Bresenham), 2);
+    assert_eq!(overlaps_3d(&lines, Raster::Lattice), 2);
+
+    let mut grid = Grid::sized(&lines);
+    for line in &lines {
+        grid.imprint(line);
+    }
+
+    // only the two flat lines reach the xy grid
+    assert_eq!(grid.count(|d| d >= 2), 1);
+    assert_eq!(grid.count(|d| d >= 1), 9);
+    assert_eq!(grid.get(&Point { x: -1, y: 2, z: 0 }), 2);
+    assert_eq!(grid.bounds, Some(Bounds { min: (-3, 0), max: (1, 4) }));
+    assert_eq!(sweep::overlaps(&lines), None);
+}
@@ -0,0 +1,279 @@
+use crate::Line;
+
+use std::collections::HashMap;
+
+#[cfg(test)]
+use crate::{Grid, Point};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+enum Dir {
+    // y = key
+    Horizontal,
+    // x = key
+    Vertical,
+    // x - y = key
+    Diagonal,
+    // x + y = key
+    Anti,
+}
+
+// A segment as an inclusive range of a parameter along the infinite line
+// (dir, key). The parameter is y for vertical lines and x for the rest.
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    dir: Dir,
+    key: i128,
+    lo: i128,
+    hi: i128,
+}
+
+impl Span {
+    fn new(line: &Line) -> Option<Span> {
+        if line.start.z != 0 || line.end.z != 0 {
+            return None;
+        }
+
+        let (sx, sy) = (line.start.x as i128, line.start.y as i128);
+        let (ex, ey) = (line.end.x as i128, line.end.y as i128);
+
+        let (dir, key, a, b) = if line.is_horizontal() {
+            (Dir::Horizontal, sy, sx, ex)
+        } else if line.is_vertical() {
+            (Dir::Vertical, sx, sy, ey)
+        } else if !line.is_diagonal() {
+            return None;
+        } else if line.slope() == Some((1, 1)) {
+            (Dir::Diagonal, sx - sy, sx, ex)
+        } else {
+            (Dir::Anti, sx + sy, sx, ex)
+        };
+
+        Some(Span { dir, key, lo: a.min(b), hi: a.max(b) })
+    }
+
+    fn point(&self, t: i128) -> (i128, i128) {
+        match self.dir {
+            Dir::Horizontal => (t, self.key),
+            Dir::Vertical => (self.key, t),
+            Dir::Diagonal => (t, t - self.key),
+            Dir::Anti => (t, self.key - t),
+        }
+    }
+
+    fn param(&self, (x, y): (i128, i128)) -> i128 {
+        match self.dir {
+            Dir::Vertical => y,
+            _ => x,
+        }
+    }
+
+    fn x_range(&self) -> (i128, i128) {
+        match self.dir {
+            Dir::Vertical => (self.key, self.key),
+            _ => (self.lo, self.hi),
+        }
+    }
+
+    fn contains(&self, p: (i128, i128)) -> bool {
+        self.point(self.param(p)) == p && (self.lo..=self.hi).contains(&self.param(p))
+    }
+
+    // The lattice point where the infinite lines of two spans from different
+    // families meet, if any.
+    fn meet(&self, other: &Span) -> Option<(i128, i128)> {
+        let (a, b) = if self.dir <= other.dir { (self, other) } else { (other, self) };
+        let (c1, c2) = (a.key, b.key);
+
+        match (a.dir, b.dir) {
+            (Dir::Horizontal, Dir::Vertical) => Some((c2, c1)),
+            (Dir::Horizontal, Dir::Diagonal) => Some((c2 + c1, c1)),
+            (Dir::Horizontal, Dir::Anti) => Some((c2 - c1, c1)),
+            (Dir::Vertical, Dir::Diagonal) => Some((c1, c1 - c2)),
+            (Dir::Vertical, Dir::Anti) => Some((c1, c2 - c1)),
+            // diagonals of different parity cross between lattice points
+            (Dir::Diagonal, Dir::Anti) if (c1 + c2) % 2 == 0 => Some(((c1 + c2) / 2, (c2 - c1) / 2)),
+            _ => None,
+        }
+    }
+}
+
+// Coverage along one infinite line: where any span lies, and where at least
+// two of them overlap. Both are sorted, disjoint, inclusive ranges.
+struct Coverage {
+    union: Vec<(i128, i128)>,
+    double: Vec<(i128, i128)>,
+}
+
+fn coverage(spans: &[Span]) -> Coverage {
+    let mut events = spans.iter().flat_map(|s| [(s.lo, 1), (s.hi + 1, -1)]).collect::<Vec<_>>();
+    events.sort_unstable();
+
+    let mut union = Vec::new();
+    let mut double = Vec::new();
+    let mut depth = 0;
+    let (mut union_start, mut double_start) = (0, 0);
+
+    for (t, delta) in events {
+        let before = depth;
+        depth += delta;
+
+        match (before, depth) {
+            (0, 1) => union_start = t,
+            (1, 0) => union.push((union_start, t - 1)),
+            (1, 2) => double_start = t,
+            (2, 1) => double.push((double_start, t - 1)),
+            _ => {}
+        }
+    }
+
+    // touching ranges come out separately when one span ends where the next
+    // starts, which does not matter for point membership
+    Coverage { union, double }
+}
+
+fn within(ranges: &[(i128, i128)], t: i128) -> bool {
+    let i = ranges.partition_point(|(_, hi)| *hi < t);
+    ranges.get(i).is_some_and(|(lo, _)| *lo <= t)
+}
+
+// Number of points covered by at least two lines, without walking any of
+// them. Lines on the same infinite line are merged into overlap ranges, and
+// crossings between different lines are found by sweeping over x, only
+// testing pairs whose x ranges overlap. None if a line is neither
+// horizontal, vertical nor at 45 degrees, or leaves the z = 0 plane.
+pub fn overlaps(lines: &[Line]) -> Option<u128> {
+    let mut families: HashMap<(Dir, i128), Vec<Span>> = HashMap::new();
+
+    for line in lines {
+        let span = Span::new(line)?;
+        families.entry((span.dir, span.key)).or_default().push(span);
+    }
+
+    let families = families.into_values().map(|spans| (spans[0], coverage(&spans))).collect::<Vec<_>>();
+
+    // one span per merged range, tagged with the family it came from
+    let mut merged = families
+        .iter()
+        .enumerate()
+        .flat_map(|(f, (span, cov))| cov.union.iter().map(move |(lo, hi)| (f, Span { lo: *lo, hi: *hi, ..*span })))
+        .collect::<Vec<_>>();
+
+    merged.sort_unstable_by_key(|(_, s)| s.x_range().0);
+
+    let mut crossings: HashMap<(i128, i128), Vec<usize>> = HashMap::new();
+    let mut active: Vec<(usize, Span)> = Vec::new();
+
+    for (f, span) in merged {
+        let (start, _) = span.x_range();
+        active.retain(|(_, s)| s.x_range().1 >= start);
+
+        for (g, other) in &active {
+            if let Some(p) = span.meet(other).filter(|p| span.contains(*p) && other.contains(*p)) {
+                let families = crossings.entry(p).or_default();
+
+                for h in [f, *g] {
+                    if !families.contains(&h) {
+                        families.push(h);
+                    }
+                }
+            }
+        }
+
+        active.push((f, span));
+    }
+
+    // every crossing counts once, overlap ranges count their points that are
+    // not crossings
+    let mut res = crossings.len() as u128;
+
+    for (_, cov) in &families {
+        res += cov.double.iter().map(|(lo, hi)| (hi - lo + 1) as u128).sum::<u128>();
+    }
+
+    for (p, fs) in &crossings {
+        for f in fs {
+            let (span, cov) = &families[*f];
+
+            if within(&cov.double, span.param(*p)) {
+                res -= 1;
+            }
+        }
+    }
+
+    Some(res)
+}
+
+#[cfg(test)]
+fn line(sx: i64, sy: i64, ex: i64, ey: i64) -> Line {
+    Line { start: Point { x: sx, y: sy, z: 0 }, end: Point { x: ex, y: ey, z: 0 } }
+}
+
+#[cfg(test)]
+fn imprinted(lines: &[Line]) -> u128 {
+    let mut grid = Grid::default();
+    for line in lines {
+        grid.imprint(line);
+    }
+
+    grid.count(|d| d >= 2)
+}
+
+#[cfg(test)]
+fn generate(count: usize, size: i64, seed: u64) -> Vec<Line> {
+    let mut rng = utils::rng::Rng::new(seed);
+    let mut next = move |n: i64| rng.below(n as u64) as i64;
+
+    (0..count)
+        .map(|_| {
+            // centred on the origin to cover negative coordinates
+            let (sx, sy) = (next(size) - size / 2, next(size) - size / 2);
+            let len = next(size / 2);
+
+            let (ex, ey) = match next(4) {
+                0 => (sx, next(size) - size / 2),
+                1 => (next(size) - size / 2, sy),
+                2 => (sx + len, sy + len),
+                _ => (sx + len, sy - len),
+            };
+
+            line(sx, sy, ex, ey)
+        })
+        .collect()
+}
+
+#[test]
+fn test_sample() {
+    let lines = utils::test_input_vec::<Line>();
+    let snapped = lines.iter().filter(|l| l.snapped()).cloned().collect::<Vec<_>>();
+
+    assert_eq!(overlaps(&snapped), Some(5));
+    assert_eq!(overlaps(&lines), Some(12));
+}
+
+#[test]
+fn test_collinear() {
+    let lines = vec![
+        line(0, 0, 6, 0),
+        line(4, 0, 9, 0),
+        line(5, 0, 5, 0),
+        line(7, 3, 2, 3),
+        // crosses both horizontal lines, once inside their overlap
+        line(5, 5, 5, 0),
+        line(1, 1, 4, 4),
+        line(3, 3, 6, 6),
+        // diagonals that meet between lattice points
+        line(0, 1, 1, 0),
+        line(0, 0, 1, 1),
+    ];
+
+    assert_eq!(overlaps(&lines), Some(imprinted(&lines)));
+    assert_eq!(overlaps(&[line(0, 0, 2, 1)]), None);
+}
+
+#[test]
+fn test_random() {
+    for seed in 1..20 {
+        let lines = generate(200, 60, seed * 0x9e37_79b9);
+        assert_eq!(overlaps(&lines), Some(imprinted(&lines)), "seed {}", seed);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::Fish;
+
+// How lanternfish age. A fish counts its timer down to 0, waits there for
+// `gestation` days, then gives birth to a fish with `newborn_timer` and goes
+// back to `reset`. With `mortality` set, a fish dies right after giving
+// birth that many times.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lifecycle {
+    pub reset: u8,
+    pub newborn_timer: u8,
+    pub gestation: u8,
+    pub mortality: Option<u32>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Lifecycle {
+        Lifecycle { reset: 6, newborn_timer: 8, gestation: 1, mortality: None }
+    }
+}
+
+impl Lifecycle {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.gestation == 0 {
+            return Err("gestation must be at least 1 day".to_string());
+        }
+
+        if self.mortality == Some(0) {
+            return Err("mortality must be at least 1 cycle".to_string());
+        }
+
+        Ok(())
+    }
+
+    // Fish are bucketed by how many births they have given, then by days
+    // left until their next birth. Timers up to `max_timer` need `slots`
+    // buckets per birth count.
+    pub fn slots(&self, max_timer: u8) -> usize {
+        max_timer.max(self.reset).max(self.newborn_timer) as usize + self.gestation as usize
+    }
+
+    pub fn cohorts(&self) -> usize {
+        self.mortality.map_or(1, |n| n as usize)
+    }
+
+    pub fn index(&self, fish: &Fish, slots: usize) -> usize {
+        let until_birth = fish.left as usize + (self.gestation - fish.waited) as usize - 1;
+        let cohort = if self.mortality.is_some() { fish.births as usize } else { 0 };
+
+        cohort * slots + until_birth
+    }
+
+    // Buckets the fish in `bucket` count towards the next day: one timer down,
+    // or on giving birth the parent's and the newborn's.
+    pub fn moves(&self, bucket: usize, slots: usize) -> impl Iterator<Item = usize> {
+        let (cohort, until_birth) = (bucket / slots, bucket % slots);
+
+        if until_birth > 0 {
+            return std::iter::once(bucket - 1).chain(None);
+        }
+
+        // parents move to the next cohort, dying once there is none left
+        let parent = if self.mortality.is_some() { cohort + 1 } else { cohort };
+        let reset = (parent < self.cohorts()).then(|| parent * slots + self.reset as usize + self.gestation as usize - 1);
+
+        std::iter::once(self.newborn_timer as usize + self.gestation as usize - 1).chain(reset)
+    }
+
+    // one day for bucketed counts
+    pub fn step(&self, counts: &[u64], slots: usize) -> Vec<u64> {
+        let mut next = vec![0; counts.len()];
+
+        for (bucket, count) in counts.iter().enumerate() {
+            for to in self.moves(bucket, slots) {
+                next[to] += count;
+            }
+        }
+
+        next
+    }
+}
+
+#[test]
+fn test_step() {
+    let life = Lifecycle::default();
+    let slots = life.slots(8);
+
+    // the default lifecycle buckets fish by timer
+    assert_eq!(slots, 9);
+    assert_eq!(life.step(&[2, 0, 0, 0, 0, 0, 0, 1, 0], slots), vec![0, 0, 0, 0, 0, 0, 3, 0, 2]);
+
+    let slow = Lifecycle { gestation: 3, mortality: Some(2), ..life };
+    let slots = slow.slots(8);
+    let fish = Fish { left: 0, waited: 1, births: 1 };
+
+    assert_eq!(slots, 11);
+    assert_eq!(slow.index(&fish, slots), slots + 1);
+
+    let mut counts = vec![0; 2 * slots];
+    counts[0] = 5;
+    counts[slots] = 7;
+    let next = slow.step(&counts, slots);
+
+    // first time mothers move on a cohort, second time ones die
+    assert_eq!(next[slots + 6 + 2], 5);
+    assert_eq!(next[8 + 2], 12);
+    assert_eq!(next.iter().sum::<u64>(), 17);
+}
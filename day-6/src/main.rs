@@ -5,42 +5,134 @@ use nom::{
     multi::separated_list1,
 };
 
+mod lifecycle;
+mod matrix;
+mod series;
+
+use lifecycle::Lifecycle;
+use matrix::{Arith, Exact, Matrix, Modulo, Wide};
 
 fn main() {
-    let school = utils::input::<School>();
-    println!("fish after 80 days: {}", school.sim_day(80));
-    println!("fish after 256 days: {}", school.fast_sim_day(256));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let value = |name: &str| {
+        args.iter().position(|arg| arg == name).map(|i| {
+            args.get(i + 1).and_then(|v| v.parse::<u64>().ok()).unwrap_or_else(|| usage())
+        })
+    };
+    let small = |name: &str| value(name).map(|v| v.try_into().unwrap_or_else(|_| usage()));
+
+    let defaults = Lifecycle::default();
+    let lifecycle = Lifecycle {
+        reset: small("--reset").unwrap_or(defaults.reset),
+        newborn_timer: small("--newborn").unwrap_or(defaults.newborn_timer),
+        gestation: small("--gestation").unwrap_or(defaults.gestation),
+        mortality: value("--mortality").map(|v| v.try_into().unwrap_or_else(|_| usage())),
+    };
+
+    if let Err(e) = lifecycle.validate() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    let modulo = value("--mod").map(|p| {
+        let arith = Modulo::new(p).unwrap_or_else(|| {
+            eprintln!("modulus must be at least 2");
+            std::process::exit(1);
+        });
+
+        (p, arith)
+    });
+
+    let school = utils::input::<School>().with_lifecycle(lifecycle);
+    // through the buckets, since the fish themselves can outgrow memory and
+    // a short lifecycle overflows u64 counts
+    for days in [80, 256] {
+        match school.population(days, &Wide) {
+            Some(count) => println!("fish after {} days: {}", days, count),
+            None => println!("fish after {} days: more than fits in u128, try --days {} --exact", days, days),
+        }
+    }
+
+    if let Some(days) = value("--days") {
+        let count = match modulo {
+            Some((p, arith)) => school.population(days, &arith).map(|n| format!("{} (mod {})", n, p)),
+            None if args.iter().any(|arg| arg == "--exact") => school.population(days, &Exact).map(|n| n.to_string()),
+            None => school.population(days, &Wide).map(|n| n.to_string()),
+        };
+
+        match count {
+            Some(count) => println!("fish after {} days: {}", days, count),
+            None => println!("fish after {} days: more than fits in u128, try --exact or --mod", days),
+        }
+    }
+
+    if args.iter().any(|arg| arg == "--growth") {
+        let rate = school.growth_rate();
+        // the series ends early for lifecycles that outgrow u64 by day 80
+        let projected = school.series().nth(80).and_then(|start| school.project(&start, 256));
+
+        println!("steady growth: {:.6} per day", rate);
+        if let Some(projected) = projected {
+            println!("projected from day 80 to 256: {:.0}", projected);
+        }
+    }
+
+    if let Some(days) = value("--csv") {
+        print!("{}", series::csv(school.series().take(days as usize + 1)));
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: day-6 [--reset n] [--newborn n] [--gestation n] [--mortality n] [--days n [--mod p | --exact]] \
+         [--csv days] [--growth]"
+    );
+    std::process::exit(1);
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct School {
     fish: Vec<Fish>,
+    lifecycle: Lifecycle,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Fish {
     left: u8,
+    // days spent at 0 so far
+    waited: u8,
+    births: u32,
 }
 
 impl School {
+    fn with_lifecycle(mut self, lifecycle: Lifecycle) -> School {
+        self.lifecycle = lifecycle;
+        self
+    }
+
+    // one Fish per fish, the reference for the bucketed versions
+    #[cfg(test)]
     fn sim_day(&self, mut days: u32) -> u64 {
+        let life = &self.lifecycle;
         let mut fish = self.fish.clone();
 
         while days > 0 {
             let mut adds = 0;
             for f in &fish {
-                if f.reproduce() {
+                if f.reproduce(life) {
                     adds += 1;
                 }
             }
 
 
             for f in &mut fish {
-                f.dec();
+                f.dec(life);
             }
 
+            fish.retain(|f| life.mortality.is_none_or(|n| f.births < n));
+
             for _ in 0..adds {
-                fish.push(Fish::default());
+                fish.push(Fish::newborn(life));
             }
 
             days -= 1;
@@ -49,47 +141,128 @@ impl School {
         fish.len() as u64
     }
 
-    fn fast_sim_day(&self, mut days: u32) -> u64 {
-        let mut lifecycle = [0u64; 9];
+    fn slots(&self) -> usize {
+        self.lifecycle.slots(self.fish.iter().map(|f| f.left).max().unwrap_or(0))
+    }
+
+    fn buckets(&self) -> Vec<u64> {
+        let slots = self.slots();
+        let mut counts = vec![0u64; self.lifecycle.cohorts() * slots];
 
         for f in &self.fish {
-            lifecycle[f.left as usize] += 1;
+            counts[self.lifecycle.index(f, slots)] += 1;
         }
 
-        while days > 0 {
-            let repro = lifecycle[0];
-            for i in 1..lifecycle.len() {
-                lifecycle[i-1] = lifecycle[i];
-            }
+        counts
+    }
 
-            lifecycle[8] = repro;
-            lifecycle[6] += repro;
+    #[cfg(test)]
+    fn fast_sim_day(&self, mut days: u32) -> u64 {
+        let slots = self.slots();
+        let mut buckets = self.buckets();
 
+        while days > 0 {
+            buckets = self.lifecycle.step(&buckets, slots);
             days -= 1;
         }
 
         let mut res = 0;
-        for section in &lifecycle {
+        for section in &buckets {
             res += *section;
         }
 
         res
     }
+
+    // next[i] = sum over j of transition[i][j] * cur[j], read off by stepping
+    // each bucket on its own
+    fn transition(&self) -> Vec<Vec<u64>> {
+        let slots = self.slots();
+        let n = self.lifecycle.cohorts() * slots;
+        let mut rows = vec![vec![0; n]; n];
+
+        for j in 0..n {
+            let mut unit = vec![0; n];
+            unit[j] = 1;
+
+            for (i, c) in self.lifecycle.step(&unit, slots).into_iter().enumerate() {
+                rows[i][j] = c;
+            }
+        }
+
+        rows
+    }
+
+    // Steps the n buckets (9 for the puzzle) one day at a time, or takes
+    // powers of the transition matrix once that is cheaper: O(n^3 log days)
+    // against O(n days). Mortality multiplies n, so the matrix is only built
+    // for day counts far beyond the buckets.
+    fn population<A: Arith>(&self, days: u64, arith: &A) -> Option<A::Num> {
+        let n = self.lifecycle.cohorts() as u128 * self.slots() as u128;
+
+        if days as u128 <= n.pow(3) * (u64::BITS - days.leading_zeros()) as u128 {
+            self.stepped(days, arith)
+        } else {
+            self.powered(days, arith)
+        }
+    }
+
+    fn stepped<A: Arith>(&self, days: u64, arith: &A) -> Option<A::Num> {
+        let slots = self.slots();
+        let mut counts = self.buckets().into_iter().map(|c| arith.num(c)).collect::<Vec<_>>();
+
+        for _ in 0..days {
+            let mut next = vec![arith.num(0); counts.len()];
+
+            for (bucket, count) in counts.iter().enumerate() {
+                for to in self.lifecycle.moves(bucket, slots) {
+                    next[to] = arith.add(&next[to], count)?;
+                }
+            }
+
+            counts = next;
+        }
+
+        counts.iter().try_fold(arith.num(0), |acc, c| arith.add(&acc, c))
+    }
+
+    fn powered<A: Arith>(&self, days: u64, arith: &A) -> Option<A::Num> {
+        let counts = self.buckets().into_iter().map(|c| arith.num(c)).collect::<Vec<_>>();
+        let step = Matrix::from_rows(&self.transition(), arith).pow(days, arith)?;
+
+        step.apply(&counts, arith)?
+            .iter()
+            .try_fold(arith.num(0), |acc, c| arith.add(&acc, c))
+    }
 }
 
 impl Fish {
-    fn dec(&mut self) {
-        self.left = self.left.checked_sub(1).unwrap_or(6);
+    fn new(left: u8) -> Fish {
+        Fish { left, waited: 0, births: 0 }
     }
+}
 
-    fn reproduce(&self) -> bool {
-        self.left == 0
+// one fish at a time, only the reference simulation needs these
+#[cfg(test)]
+impl Fish {
+    fn newborn(life: &Lifecycle) -> Fish {
+        Fish::new(life.newborn_timer)
+    }
+
+    fn dec(&mut self, life: &Lifecycle) {
+        if self.left > 0 {
+            self.left -= 1;
+        } else if self.reproduce(life) {
+            self.left = life.reset;
+            self.waited = 0;
+            self.births += 1;
+        } else {
+            self.waited += 1;
+        }
     }
-}
 
-impl Default for Fish {
-    fn default() -> Fish {
-        Fish { left: 8 }
+    fn reproduce(&self, life: &Lifecycle) -> bool {
+        self.left == 0 && self.waited + 1 == life.gestation
     }
 }
 
@@ -98,7 +271,7 @@ impl utils::Parsable for School {
     fn parse(input: &str) -> IResult<&str, School> {
         let (input, fish) = separated_list1(tag(","), Fish::parse)(input)?;
 
-        Ok((input, School { fish }))
+        Ok((input, School { fish, lifecycle: Lifecycle::default() }))
     }
 }
 
@@ -106,7 +279,7 @@ impl utils::Parsable for Fish {
     fn parse(input: &str) -> IResult<&str, Fish> {
         let (input, left) = nom::character::complete::u8(input)?;
 
-        Ok((input, Fish { left }))
+        Ok((input, Fish::new(left)))
     }
 }
 
@@ -118,12 +291,13 @@ fn test_input() {
         s,
         School {
             fish: vec![
-                Fish { left: 3 },
-                Fish { left: 4 },
-                Fish { left: 3 },
-                Fish { left: 1 },
-                Fish { left: 2 },
-            ]
+                Fish::new(3),
+                Fish::new(4),
+                Fish::new(3),
+                Fish::new(1),
+                Fish::new(2),
+            ],
+            lifecycle: Lifecycle::default(),
         }
     )
 }
@@ -144,3 +318,63 @@ fn test_part2() {
     assert_eq!(s.fast_sim_day(80), 5934);
     assert_eq!(s.fast_sim_day(256), 26984457539);
 }
+
+#[test]
+fn test_population() {
+    let s = utils::test_input::<School>();
+
+    for days in 0..300 {
+        assert_eq!(s.population(days as u64, &Wide), Some(s.fast_sim_day(days) as u128));
+    }
+
+    assert_eq!(s.population(256, &Wide), Some(26984457539));
+
+    let exact = s.population(5000, &Exact).unwrap();
+    assert_eq!(s.population(5000, &Wide), None);
+    assert_eq!(s.population(5000, &Modulo::new(1_000_000_007).unwrap()), Some(exact.rem(1_000_000_007)));
+
+    for days in [0, 1, 80, 256, 1000] {
+        assert_eq!(s.stepped(days, &Exact), s.powered(days, &Exact));
+    }
+
+    // only feasible with the matrix power
+    assert!(s.population(1_000_000_000_000_000_000, &Modulo::new(998_244_353).unwrap()).unwrap() < 998_244_353);
+}
+
+#[test]
+fn test_lifecycle() {
+    let s = utils::test_input::<School>();
+
+    // the puzzle's lifecycle spelled out changes nothing
+    let same = s.clone().with_lifecycle(Lifecycle { reset: 6, newborn_timer: 8, gestation: 1, mortality: None });
+    assert_eq!(same.fast_sim_day(256), 26984457539);
+
+    let variants = [
+        Lifecycle { reset: 4, newborn_timer: 5, ..Lifecycle::default() },
+        Lifecycle { gestation: 3, ..Lifecycle::default() },
+        Lifecycle { mortality: Some(2), ..Lifecycle::default() },
+        Lifecycle { reset: 2, newborn_timer: 11, gestation: 2, mortality: Some(3) },
+    ];
+
+    for life in variants {
+        let s = s.clone().with_lifecycle(life.clone());
+
+        for days in [0, 1, 5, 18, 40, 80] {
+            assert_eq!(s.fast_sim_day(days), s.sim_day(days), "{:?} after {}", life, days);
+            assert_eq!(s.population(days as u64, &Wide), Some(s.fast_sim_day(days) as u128));
+        }
+    }
+
+    // a single fish that dies with its first child leaves a line of one
+    let single = School { fish: vec![Fish::new(0)], lifecycle: Lifecycle { mortality: Some(1), ..Lifecycle::default() } };
+    assert_eq!(single.sim_day(1000), 1);
+    assert_eq!(single.population(1_000_000, &Wide), Some(1));
+
+    // far more cohorts than anyone lives through, without a matrix of them
+    let long = s.clone().with_lifecycle(Lifecycle { mortality: Some(10_000), ..Lifecycle::default() });
+    assert_eq!(long.population(256, &Wide), Some(26984457539));
+
+    let mortal = s.clone().with_lifecycle(Lifecycle { reset: 2, newborn_timer: 11, gestation: 2, mortality: Some(3) });
+    assert_eq!(mortal.stepped(500, &Exact), mortal.powered(500, &Exact));
+    assert!(Lifecycle { gestation: 0, ..Lifecycle::default() }.validate().is_err());
+}
@@ -0,0 +1,164 @@
+use utils::big::Big;
+
+// The number system a population is counted in. Operations return None
+// when the result does not fit.
+pub trait Arith {
+    type Num: Clone;
+
+    fn num(&self, n: u64) -> Self::Num;
+    fn add(&self, a: &Self::Num, b: &Self::Num) -> Option<Self::Num>;
+    fn mul(&self, a: &Self::Num, b: &Self::Num) -> Option<Self::Num>;
+}
+
+// u128, giving up on overflow
+pub struct Wide;
+
+// arbitrary precision
+pub struct Exact;
+
+// remainders modulo a fixed number
+pub struct Modulo(u64);
+
+impl Modulo {
+    // None below 2, where remainders say nothing
+    pub fn new(modulus: u64) -> Option<Modulo> {
+        (modulus >= 2).then_some(Modulo(modulus))
+    }
+}
+
+impl Arith for Wide {
+    type Num = u128;
+
+    fn num(&self, n: u64) -> u128 {
+        n as u128
+    }
+
+    fn add(&self, a: &u128, b: &u128) -> Option<u128> {
+        a.checked_add(*b)
+    }
+
+    fn mul(&self, a: &u128, b: &u128) -> Option<u128> {
+        a.checked_mul(*b)
+    }
+}
+
+impl Arith for Exact {
+    type Num = Big;
+
+    fn num(&self, n: u64) -> Big {
+        Big::from(n)
+    }
+
+    fn add(&self, a: &Big, b: &Big) -> Option<Big> {
+        Some(a + b)
+    }
+
+    fn mul(&self, a: &Big, b: &Big) -> Option<Big> {
+        Some(a * b)
+    }
+}
+
+impl Arith for Modulo {
+    type Num = u64;
+
+    fn num(&self, n: u64) -> u64 {
+        n % self.0
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> Option<u64> {
+        Some(((*a as u128 + *b as u128) % self.0 as u128) as u64)
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> Option<u64> {
+        Some((*a as u128 * *b as u128 % self.0 as u128) as u64)
+    }
+}
+
+// Square matrix, row-major.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Matrix<T> {
+    n: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Matrix<T> {
+    pub fn from_rows<A: Arith<Num = T>>(rows: &[Vec<u64>], arith: &A) -> Matrix<T> {
+        Matrix {
+            n: rows.len(),
+            cells: rows.iter().flatten().map(|c| arith.num(*c)).collect(),
+        }
+    }
+
+    fn identity<A: Arith<Num = T>>(n: usize, arith: &A) -> Matrix<T> {
+        let cells = (0..n * n).map(|i| arith.num((i / n == i % n) as u64)).collect();
+        Matrix { n, cells }
+    }
+
+    fn mul<A: Arith<Num = T>>(&self, rhs: &Matrix<T>, arith: &A) -> Option<Matrix<T>> {
+        let n = self.n;
+        let mut cells = Vec::with_capacity(n * n);
+
+        for r in 0..n {
+            for c in 0..n {
+                let mut acc = arith.num(0);
+
+                for k in 0..n {
+                    acc = arith.add(&acc, &arith.mul(&self.cells[r * n + k], &rhs.cells[k * n + c])?)?;
+                }
+
+                cells.push(acc);
+            }
+        }
+
+        Some(Matrix { n, cells })
+    }
+
+    // square and multiply, O(n^3 log exp)
+    pub fn pow<A: Arith<Num = T>>(&self, mut exp: u64, arith: &A) -> Option<Matrix<T>> {
+        let mut res = Matrix::identity(self.n, arith);
+        let mut base = self.clone();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                res = res.mul(&base, arith)?;
+            }
+
+            exp >>= 1;
+
+            if exp > 0 {
+                base = base.mul(&base, arith)?;
+            }
+        }
+
+        Some(res)
+    }
+
+    pub fn apply<A: Arith<Num = T>>(&self, v: &[T], arith: &A) -> Option<Vec<T>> {
+        (0..self.n)
+            .map(|r| {
+                (0..self.n).try_fold(arith.num(0), |acc, k| arith.add(&acc, &arith.mul(&self.cells[r * self.n + k], &v[k])?))
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_pow() {
+    // fibonacci
+    let fib = Matrix::from_rows(&[vec![1, 1], vec![1, 0]], &Wide);
+
+    assert_eq!(fib.pow(10, &Wide).unwrap().cells, vec![89, 55, 55, 34]);
+    assert_eq!(fib.pow(0, &Wide).unwrap().cells, vec![1, 0, 0, 1]);
+    // F(186) is the largest fibonacci number below 2^128
+    assert_eq!(fib.pow(185, &Wide).unwrap().cells[0], 332825110087067562321196029789634457848);
+    assert_eq!(fib.pow(186, &Wide), None);
+
+    let big = Matrix::from_rows(&[vec![1, 1], vec![1, 0]], &Exact).pow(300, &Exact).unwrap();
+    assert_eq!(big.cells[1].to_string(), "222232244629420445529739893461909967206666939096499764990979600");
+
+    let p = Modulo::new(1_000_000_007).unwrap();
+    let modp = Matrix::from_rows(&[vec![1, 1], vec![1, 0]], &p).pow(300, &p).unwrap();
+    assert_eq!(modp.cells[1], big.cells[1].rem(1_000_000_007));
+    assert!(Modulo::new(0).is_none());
+    assert!(Modulo::new(1).is_none());
+}
@@ -0,0 +1,184 @@
+use crate::School;
+
+#[cfg(test)]
+use crate::matrix::Wide;
+
+// Fish alive on one day, counted by timer. Fish waiting out their gestation
+// count as timer 0.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Census {
+    pub day: u64,
+    pub timers: Vec<u64>,
+}
+
+impl Census {
+    pub fn total(&self) -> u64 {
+        self.timers.iter().sum()
+    }
+}
+
+// One census per day starting at day 0. Ends before the counts could
+// overflow u64, which for the puzzle is somewhere past day 400.
+pub struct Series<'a> {
+    school: &'a School,
+    slots: usize,
+    day: u64,
+    buckets: Option<Vec<u64>>,
+}
+
+impl<'a> Series<'a> {
+    pub fn new(school: &'a School) -> Series<'a> {
+        Series { school, slots: school.slots(), day: 0, buckets: Some(school.buckets()) }
+    }
+
+    fn census(&self, buckets: &[u64]) -> Census {
+        let wait = self.school.lifecycle.gestation as usize - 1;
+        let mut timers = vec![0; self.slots - wait];
+
+        for cohort in buckets.chunks(self.slots) {
+            for (j, count) in cohort.iter().enumerate() {
+                timers[j.saturating_sub(wait)] += count;
+            }
+        }
+
+        Census { day: self.day, timers }
+    }
+}
+
+impl<'a> Iterator for Series<'a> {
+    type Item = Census;
+
+    fn next(&mut self) -> Option<Census> {
+        let buckets = self.buckets.take()?;
+        let census = self.census(&buckets);
+
+        // a fish has at most one child a day, so the population at most doubles
+        if census.total() <= u64::MAX / 2 {
+            self.buckets = Some(self.school.lifecycle.step(&buckets, self.slots));
+        }
+
+        self.day += 1;
+        Some(census)
+    }
+}
+
+pub fn csv(census: impl Iterator<Item = Census>) -> String {
+    let mut census = census.peekable();
+    let width = census.peek().map_or(0, |c| c.timers.len());
+
+    let mut res = String::from("day,total");
+    for t in 0..width {
+        res.push_str(&format!(",timer_{}", t));
+    }
+    res.push('\n');
+
+    for c in census {
+        res.push_str(&format!("{},{}", c.day, c.total()));
+        for n in &c.timers {
+            res.push_str(&format!(",{}", n));
+        }
+        res.push('\n');
+    }
+
+    res
+}
+
+impl School {
+    pub fn series(&self) -> Series<'_> {
+        Series::new(self)
+    }
+
+    // The steady state factor the population grows by each day, the
+    // dominant eigenvalue of the transition matrix. Power iteration runs on
+    // (M + I) / 2, which shares M's eigenvectors but does not oscillate when
+    // every lifecycle loop has a common period. It stops as soon as two
+    // estimates agree to 1e-15, a few hundred rounds for the puzzle; the
+    // round limit only matters for lifecycles that converge very slowly.
+    pub fn growth_rate(&self) -> f64 {
+        const ROUNDS: usize = 100_000;
+
+        let rows = self.transition();
+        let n = rows.len();
+        let mut v = vec![1.0 / n as f64; n];
+        let mut rate = 0.0;
+
+        for _ in 0..ROUNDS {
+            let next = (0..n)
+                .map(|i| (v[i] + (0..n).map(|j| rows[i][j] as f64 * v[j]).sum::<f64>()) / 2.0)
+                .collect::<Vec<_>>();
+
+            let sum = next.iter().sum::<f64>();
+            if sum == 0.0 {
+                return 0.0;
+            }
+
+            let next_rate = 2.0 * sum - 1.0;
+            v = next.into_iter().map(|x| x / sum).collect();
+
+            if (next_rate - rate).abs() < 1e-15 {
+                return next_rate;
+            }
+
+            rate = next_rate;
+        }
+
+        rate
+    }
+
+    // population on `day`, assuming steady state growth from `census`. None
+    // for days before the census.
+    pub fn project(&self, census: &Census, day: u64) -> Option<f64> {
+        let ahead = day.checked_sub(census.day)?;
+        Some(census.total() as f64 * self.growth_rate().powf(ahead as f64))
+    }
+}
+
+#[test]
+fn test_series() {
+    let s = utils::test_input::<School>();
+    let series = s.series().take(20).collect::<Vec<_>>();
+
+    assert_eq!(series[0], Census { day: 0, timers: vec![0, 1, 1, 2, 1, 0, 0, 0, 0] });
+    assert_eq!(series[2].timers, vec![1, 2, 1, 0, 0, 0, 1, 0, 1]);
+    assert_eq!(series[18].total(), 26);
+    assert_eq!(s.series().count(), 483);
+    assert_eq!(s.series().last().map(|c| c.total()), Some(s.fast_sim_day(482)));
+
+    let csv = csv(s.series().take(2));
+    assert_eq!(
+        csv,
+        "day,total,timer_0,timer_1,timer_2,timer_3,timer_4,timer_5,timer_6,timer_7,timer_8\n\
+         0,5,0,1,1,2,1,0,0,0,0\n\
+         1,5,1,1,2,1,0,0,0,0,0\n"
+    );
+}
+
+#[test]
+fn test_growth_rate() {
+    use crate::Lifecycle;
+
+    let s = utils::test_input::<School>();
+    let rate = s.growth_rate();
+
+    // every fish leaves a child every 7 days, starting after 9, so x^9 = x^2 + 1
+    assert!((rate.powi(9) - rate.powi(2) - 1.0).abs() < 1e-12);
+
+    // the other modes fade by only about 1% a day, so compare far out
+    let ratio = s.population(900, &Wide).unwrap() as f64 / s.population(899, &Wide).unwrap() as f64;
+    assert!((rate - ratio).abs() < 1e-5);
+
+    let day = s.series().nth(200).unwrap();
+    let projected = s.project(&day, 256).unwrap();
+    assert!((projected / 26984457539.0 - 1.0).abs() < 0.01);
+    assert_eq!(s.project(&day, 200), Some(day.total() as f64));
+    assert_eq!(s.project(&day, 199), None);
+
+    // both loops have even length, so the population swings between days
+    let even = s.with_lifecycle(Lifecycle { reset: 1, newborn_timer: 3, ..Lifecycle::default() });
+    let rate = even.growth_rate();
+    let ratio = even.population(202, &Wide).unwrap() as f64 / even.population(200, &Wide).unwrap() as f64;
+    assert!((rate * rate - ratio).abs() < 1e-8);
+
+    let mortal = utils::test_input::<School>().with_lifecycle(Lifecycle { mortality: Some(1), ..Lifecycle::default() });
+    assert!((mortal.growth_rate() - 1.0).abs() < 1e-9);
+}
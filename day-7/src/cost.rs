@@ -0,0 +1,207 @@
+use crate::{sum, Alignment, Crabs};
+
+// Fuel a crab burns to move some distance. Crabs are numbered in input order
+// so costs can differ between them. Distances come from u32 positions, so
+// every cost here is exact: quadratic stays below 2^64 and weighted below
+// 2^96.
+pub trait CostFn {
+    fn fuel(&self, crab: usize, dist: u64) -> u128;
+
+    // Whether the fuel of each of `crabs` crabs is convex and nondecreasing
+    // for distances up to `span`. The total over all crabs is then convex in
+    // the position they meet at.
+    fn convex(&self, crabs: usize, span: u64) -> bool;
+}
+
+// one unit per step
+pub struct Linear;
+
+// each step costs one more than the last
+pub struct Triangular;
+
+// the square of the distance
+pub struct Quadratic;
+
+// never more than `cap`, whatever the distance
+pub struct Capped<C> {
+    pub inner: C,
+    pub cap: u64,
+}
+
+// the fuel of each crab multiplied by its weight
+pub struct Weighted<C> {
+    pub inner: C,
+    pub weights: Vec<u32>,
+}
+
+impl CostFn for Linear {
+    fn fuel(&self, _: usize, dist: u64) -> u128 {
+        dist as u128
+    }
+
+    fn convex(&self, _: usize, _: u64) -> bool {
+        true
+    }
+}
+
+impl CostFn for Triangular {
+    fn fuel(&self, _: usize, dist: u64) -> u128 {
+        sum(dist) as u128
+    }
+
+    fn convex(&self, _: usize, _: u64) -> bool {
+        true
+    }
+}
+
+impl CostFn for Quadratic {
+    fn fuel(&self, _: usize, dist: u64) -> u128 {
+        dist as u128 * dist as u128
+    }
+
+    fn convex(&self, _: usize, _: u64) -> bool {
+        true
+    }
+}
+
+impl<C: CostFn> CostFn for Capped<C> {
+    fn fuel(&self, crab: usize, dist: u64) -> u128 {
+        self.inner.fuel(crab, dist).min(self.cap as u128)
+    }
+
+    // flattening out at the cap bends the wrong way, unless no crab gets there
+    fn convex(&self, crabs: usize, span: u64) -> bool {
+        self.inner.convex(crabs, span) && (0..crabs).all(|c| self.inner.fuel(c, span) <= self.cap as u128)
+    }
+}
+
+impl<C: CostFn> CostFn for Weighted<C> {
+    fn fuel(&self, crab: usize, dist: u64) -> u128 {
+        self.weights[crab] as u128 * self.inner.fuel(crab, dist)
+    }
+
+    fn convex(&self, crabs: usize, span: u64) -> bool {
+        self.inner.convex(crabs, span)
+    }
+}
+
+impl CostFn for Box<dyn CostFn> {
+    fn fuel(&self, crab: usize, dist: u64) -> u128 {
+        (**self).fuel(crab, dist)
+    }
+
+    fn convex(&self, crabs: usize, span: u64) -> bool {
+        (**self).convex(crabs, span)
+    }
+}
+
+// Any function of the distance, the same for every crab. Convexity is checked
+// by trying every distance.
+impl<F: Fn(u64) -> u64> CostFn for F {
+    fn fuel(&self, _: usize, dist: u64) -> u128 {
+        self(dist) as u128
+    }
+
+    fn convex(&self, _: usize, span: u64) -> bool {
+        let mut prev = (self(0), 0);
+
+        for d in 1..=span {
+            let (last, step) = prev;
+            let next = self(d);
+
+            if next < last || next - last < step {
+                return false;
+            }
+
+            prev = (next, next - last);
+        }
+
+        true
+    }
+}
+
+impl Crabs {
+    // Ternary search for convex costs, every position otherwise.
+    pub fn solve<C: CostFn + ?Sized>(&self, cost: &C) -> Alignment {
+        let (min, max) = self.range();
+
+        if cost.convex(self.pos.len(), (max - min) as u64) {
+            self.ternary(cost)
+        } else {
+            self.brute_force(cost)
+        }
+    }
+
+    // Narrows down on the leftmost optimum, which for a convex total never
+    // lies past the higher of two probes, or past the right one on a tie.
+    fn ternary<C: CostFn + ?Sized>(&self, cost: &C) -> Alignment {
+        let (mut lo, mut hi) = self.range();
+
+        while hi - lo > 2 {
+            let third = (hi - lo) / 3;
+            let (a, b) = (lo + third, hi - third);
+
+            match self.fuel_for(a, cost).cmp(&self.fuel_for(b, cost)) {
+                std::cmp::Ordering::Less => hi = b - 1,
+                std::cmp::Ordering::Greater => lo = a + 1,
+                std::cmp::Ordering::Equal => hi = b,
+            }
+        }
+
+        self.best(lo..=hi, cost)
+    }
+}
+
+#[cfg(test)]
+use crate::generate;
+
+#[test]
+fn test_costs() {
+    let crabs = utils::test_input::<Crabs>();
+
+    assert_eq!(crabs.solve(&Linear), crabs.linear());
+    assert_eq!(crabs.solve(&Triangular), crabs.triangular());
+    assert_eq!(crabs.solve(&Quadratic), Alignment { position: 5, fuel: 291 });
+
+    // the heaviest crab at 16 pulls the others over
+    let mut weights = vec![1; crabs.pos.len()];
+    weights[0] = 20;
+    let heavy = Weighted { inner: Linear, weights };
+    assert_eq!(crabs.solve(&heavy), Alignment { position: 16, fuel: 111 });
+
+    assert!(Capped { inner: Triangular, cap: 120 }.convex(10, 15));
+    assert!(!Capped { inner: Triangular, cap: 50 }.convex(10, 15));
+    assert!(!(|d: u64| d.min(3)).convex(1, 15));
+    assert!((|d: u64| d * d * d).convex(1, 15));
+    assert!(!(|d: u64| 10 - d.min(10)).convex(1, 1));
+
+    // two heavy crabs far apart meet in the middle, burning more than a u64
+    let far = Crabs { pos: vec![0, u32::MAX] };
+    let heavy = Weighted { inner: Quadratic, weights: vec![u32::MAX, u32::MAX] };
+    let (a, b) = (u32::MAX as u128 / 2, u32::MAX as u128 / 2 + 1);
+
+    assert_eq!(far.solve(&heavy), Alignment { position: a as u32, fuel: u32::MAX as u128 * (a * a + b * b) });
+}
+
+#[test]
+fn test_search() {
+    for seed in 1..50 {
+        let crabs = generate(1 + seed as usize % 30, 0, 200, seed * 0x9e37_79b9);
+        let weights = generate(crabs.pos.len(), 0, 10, seed).pos;
+
+        let costs: Vec<Box<dyn CostFn>> = vec![
+            Box::new(Linear),
+            Box::new(Triangular),
+            Box::new(Quadratic),
+            Box::new(|d: u64| d * d * d),
+            Box::new(Weighted { inner: Triangular, weights }),
+            // convex until the cap, then scanned
+            Box::new(Capped { inner: Quadratic, cap: 2000 }),
+            Box::new(|d: u64| (d % 7) * d),
+        ];
+
+        for (i, cost) in costs.iter().enumerate() {
+            assert_eq!(crabs.solve(cost), crabs.brute_force(cost), "seed {} cost {}", seed, i);
+        }
+    }
+}
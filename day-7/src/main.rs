@@ -5,90 +5,158 @@ use nom::{
     bytes::complete::tag,
 };
 
-fn main() {
-    println!("minimum fuel cost: {}", utils::input::<Crabs>().reposition_fuel(linear_cost));
-    println!("minimum componded fuel cost: {}", utils::input::<Crabs>().reposition_fuel(componded_cost));
-}
-
-#[derive(Debug, PartialEq, Clone)]
-struct Crabs {
-    pos: Vec<u32>,
+mod cost;
+
+use cost::{Capped, CostFn, Linear, Quadratic, Triangular, Weighted};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: day-7 [--verify] [--cost linear|triangular|quadratic] [--cap n] [--weights w,w,..]\n\
+         \n\
+         without --cost both puzzle parts are solved exactly, --cap limits the\n\
+         fuel any one crab burns, --weights multiplies each crab's fuel in input\n\
+         order"
+    );
+    std::process::exit(1)
 }
 
-impl Crabs {
-    fn reposition_fuel(&self, dist: impl Fn(u32, u32) -> u32 + Clone + Copy) -> u32 {
-        let mut pos = self.pos.iter().sum::<u32>() / self.pos.len() as u32;
-
-        let cur = self.fuel_for(pos, dist);
-        let up = self.fuel_for(pos+1, dist);
-        let down = self.fuel_for(pos-1, dist);
-
-        if cur < up && cur < down {
-            return cur;
+fn main() {
+    let mut verify = false;
+    let mut cost = None;
+    let mut cap = None;
+    let mut weights = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--verify" => verify = true,
+            "--cost" => cost = Some(args.next().unwrap_or_else(|| usage())),
+            "--cap" => cap = Some(args.next().and_then(|n| n.parse::<u64>().ok()).unwrap_or_else(|| usage())),
+            "--weights" => {
+                let list = args.next().unwrap_or_else(|| usage());
+                let parsed = list.split(',').map(|w| w.trim().parse::<u32>()).collect::<Result<Vec<_>, _>>();
+                weights = Some(parsed.unwrap_or_else(|_| usage()));
+            }
+            _ => usage(),
         }
+    }
 
-        if cur > down {
-            pos -= 1;
-            let mut cur = cur;
-            let mut next = down;
-
-            while cur > next {
-                cur = self.fuel_for(pos, dist);
-                next = self.fuel_for(pos - 1, dist);
-
-                pos -= 1;
-            }
+    let crabs = utils::input::<Crabs>();
 
-            return cur;
-        } else {
-            let mut cur = cur;
-            let mut next = up;
+    if cost.is_none() && cap.is_none() && weights.is_none() {
+        let linear = crabs.linear();
+        let triangular = crabs.triangular();
 
-            while cur > next {
-                cur = self.fuel_for(pos, dist);
-                next = self.fuel_for(pos + 1, dist);
+        println!("minimum fuel cost: {} (at {})", linear.fuel, linear.position);
+        println!("minimum componded fuel cost: {} (at {})", triangular.fuel, triangular.position);
 
-                pos += 1;
-            }
+        if verify {
+            assert_eq!(crabs.brute_force(&Linear).fuel, linear.fuel);
+            assert_eq!(crabs.brute_force(&Triangular), triangular);
+            println!("verified against every position");
+        }
 
-            return cur;
+        return;
+    }
 
+    let name = cost.unwrap_or_else(|| "linear".to_string());
+    let mut cost: Box<dyn CostFn> = match name.as_str() {
+        "linear" => Box::new(Linear),
+        "triangular" => Box::new(Triangular),
+        "quadratic" => Box::new(Quadratic),
+        _ => usage(),
+    };
+
+    if let Some(weights) = weights {
+        if weights.len() != crabs.pos.len() {
+            eprintln!("expected {} weights, got {}", crabs.pos.len(), weights.len());
+            std::process::exit(1);
         }
+
+        cost = Box::new(Weighted { inner: cost, weights });
     }
 
-    fn fuel_for(&self, pos: u32, dist: impl Fn(u32, u32) -> u32) -> u32 {
-        let mut res = 0;
+    if let Some(cap) = cap {
+        cost = Box::new(Capped { inner: cost, cap });
+    }
 
-        for p in &self.pos {
-            res += dist(pos, *p);
-        }
+    let best = crabs.solve(&cost);
+    println!("minimum {} fuel cost: {} (at {})", name, best.fuel, best.position);
 
-        res
+    if verify {
+        assert_eq!(crabs.brute_force(&cost), best);
+        println!("verified against every position");
     }
 }
 
-fn linear_cost(a: u32, b: u32) -> u32 {
-    diff(a, b)
+#[derive(Debug, PartialEq, Clone)]
+struct Crabs {
+    pos: Vec<u32>,
 }
 
-fn sum(n: u32) -> u32 {
-    (n * (n + 1)) / 2
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Alignment {
+    position: u32,
+    // summed over every crab, which can take more than a u64
+    fuel: u128,
 }
 
-fn componded_cost(a: u32, b: u32) -> u32 {
-    let min = a.min(b);
-    let max = a.max(b);
+// The solvers all expect at least one crab, which parsing guarantees.
+impl Crabs {
+    // The sum of distances is smallest at the median.
+    fn linear(&self) -> Alignment {
+        let mut pos = self.pos.clone();
+        let mid = (pos.len() - 1) / 2;
+        let (_, median, _) = pos.select_nth_unstable(mid);
 
-    if min == max {
-        return 0;
+        self.align(*median, &Linear)
     }
 
+    // The sum of n(n+1)/2 costs is within half a step of the mean, so the
+    // optimum is at the mean rounded one way or the other.
+    fn triangular(&self) -> Alignment {
+        let total = self.pos.iter().map(|p| *p as u64).sum::<u64>();
+        let floor = (total / self.pos.len() as u64) as u32;
+        let ceil = total.div_ceil(self.pos.len() as u64) as u32;
 
-    sum(max - min)
+        self.best(floor..=ceil, &Triangular)
+    }
+
+    // every position between the outermost crabs, for checking the solvers
+    fn brute_force<C: CostFn + ?Sized>(&self, cost: &C) -> Alignment {
+        let (min, max) = self.range();
+        self.best(min..=max, cost)
+    }
+
+    fn range(&self) -> (u32, u32) {
+        (*self.pos.iter().min().unwrap(), *self.pos.iter().max().unwrap())
+    }
+
+    // the leftmost of the cheapest positions
+    fn best<C: CostFn + ?Sized>(&self, positions: std::ops::RangeInclusive<u32>, cost: &C) -> Alignment {
+        positions
+            .map(|p| self.align(p, cost))
+            .min_by_key(|a| (a.fuel, a.position))
+            .unwrap()
+    }
+
+    fn align<C: CostFn + ?Sized>(&self, position: u32, cost: &C) -> Alignment {
+        Alignment { position, fuel: self.fuel_for(position, cost) }
+    }
+
+    fn fuel_for<C: CostFn + ?Sized>(&self, pos: u32, cost: &C) -> u128 {
+        let mut res = 0u128;
+
+        for (crab, p) in self.pos.iter().enumerate() {
+            res += cost.fuel(crab, pos.abs_diff(*p) as u64);
+        }
+
+        res
+    }
 }
 
-fn diff(a: u32, b: u32) -> u32 {
-    b.checked_sub(a).unwrap_or_else(|| a - b)
+fn sum(n: u64) -> u64 {
+    (n * (n + 1)) / 2
 }
 
 impl utils::Parsable for Crabs {
@@ -114,19 +182,62 @@ fn test_input() {
 fn test_output_part1() {
     let crabs = utils::test_input::<Crabs>();
 
-    assert_eq!(crabs.reposition_fuel(linear_cost), 37);
+    assert_eq!(crabs.linear(), Alignment { position: 2, fuel: 37 });
+    assert_eq!(crabs.brute_force(&Linear), crabs.linear());
 }
 
 #[test]
 fn test_output_part2() {
     let crabs = utils::test_input::<Crabs>();
 
-    assert_eq!(crabs.reposition_fuel(componded_cost), 168);
+    assert_eq!(crabs.triangular(), Alignment { position: 5, fuel: 168 });
+    assert_eq!(crabs.brute_force(&Triangular), crabs.triangular());
 }
 
 #[test]
 fn test_componded() {
-    assert_eq!(componded_cost(16, 5), 66);
-    assert_eq!(componded_cost(5, 16), 66);
-    assert_eq!(componded_cost(5, 5), 0);
+    assert_eq!(Triangular.fuel(0, 16 - 5), 66);
+    assert_eq!(Triangular.fuel(0, 0), 0);
+}
+
+#[cfg(test)]
+fn generate(count: usize, base: u32, spread: u32, seed: u64) -> Crabs {
+    let mut rng = utils::rng::Rng::new(seed);
+
+    Crabs { pos: (0..count).map(|_| base + rng.below(spread as u64) as u32).collect() }
+}
+
+#[test]
+fn test_exact() {
+    for seed in 1..50 {
+        let crabs = generate(1 + seed as usize % 30, 0, 100, seed * 0x9e37_79b9);
+
+        assert_eq!(crabs.linear().fuel, crabs.brute_force(&Linear).fuel, "seed {}", seed);
+        assert_eq!(crabs.triangular(), crabs.brute_force(&Triangular), "seed {}", seed);
+    }
+
+    // the old walk stepped below zero here
+    let zeros = Crabs { pos: vec![0, 0, 0, 1] };
+    assert_eq!(zeros.linear(), Alignment { position: 0, fuel: 1 });
+    assert_eq!(zeros.triangular(), Alignment { position: 0, fuel: 1 });
+}
+
+#[test]
+fn test_large_positions() {
+    let crabs = generate(200, u32::MAX - 1000, 1000, 0x2545_f491);
+    assert_eq!(crabs.triangular(), crabs.brute_force(&Triangular));
+    assert_eq!(crabs.linear().fuel, crabs.brute_force(&Linear).fuel);
+
+    let far = Crabs { pos: vec![0, u32::MAX] };
+    let half = u32::MAX as u64 / 2;
+
+    assert_eq!(far.linear(), Alignment { position: 0, fuel: u32::MAX as u128 });
+    assert_eq!(far.triangular(), Alignment { position: half as u32, fuel: (sum(half) + sum(half + 1)) as u128 });
+
+    // eight crabs at each end already burn more than a u64 holds
+    let ends = Crabs { pos: [[0; 8], [u32::MAX; 8]].concat() };
+    let fuel = 8 * (sum(half) as u128 + sum(half + 1) as u128);
+
+    assert!(fuel > u64::MAX as u128);
+    assert_eq!(ends.triangular(), Alignment { position: half as u32, fuel });
 }
@@ -0,0 +1,160 @@
+use std::fmt;
+use std::ops::{Add, Mul};
+
+// Unsigned integer of any size, little-endian 64-bit limbs without
+// trailing zero limbs.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Big {
+    limbs: Vec<u64>,
+}
+
+impl Big {
+    pub fn from_limbs(limbs: &[u64]) -> Big {
+        let mut limbs = limbs.to_vec();
+
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+
+        Big { limbs }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn rem(&self, div: u64) -> u64 {
+        self.clone().div_rem_small(div)
+    }
+
+    // divides in place, returning the remainder
+    fn div_rem_small(&mut self, div: u64) -> u64 {
+        let mut rem = 0u128;
+
+        for limb in self.limbs.iter_mut().rev() {
+            let cur = (rem << 64) | *limb as u128;
+            *limb = (cur / div as u128) as u64;
+            rem = cur % div as u128;
+        }
+
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+
+        rem as u64
+    }
+}
+
+impl From<u64> for Big {
+    fn from(n: u64) -> Big {
+        Big::from_limbs(&[n])
+    }
+}
+
+impl From<u128> for Big {
+    fn from(n: u128) -> Big {
+        Big::from_limbs(&[n as u64, (n >> 64) as u64])
+    }
+}
+
+impl TryFrom<&Big> for u128 {
+    type Error = ();
+
+    fn try_from(big: &Big) -> Result<u128, ()> {
+        match big.limbs[..] {
+            [] => Ok(0),
+            [lo] => Ok(lo as u128),
+            [lo, hi] => Ok((hi as u128) << 64 | lo as u128),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Add for &Big {
+    type Output = Big;
+
+    fn add(self, rhs: &Big) -> Big {
+        let len = self.limbs.len().max(rhs.limbs.len());
+        let mut res = Vec::with_capacity(len + 1);
+        let mut carry = 0u128;
+
+        for i in 0..len {
+            let cur = *self.limbs.get(i).unwrap_or(&0) as u128 + *rhs.limbs.get(i).unwrap_or(&0) as u128 + carry;
+            res.push(cur as u64);
+            carry = cur >> 64;
+        }
+
+        res.push(carry as u64);
+        Big::from_limbs(&res)
+    }
+}
+
+impl Mul for &Big {
+    type Output = Big;
+
+    fn mul(self, rhs: &Big) -> Big {
+        let mut res = vec![0u64; self.limbs.len() + rhs.limbs.len()];
+
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u128;
+
+            for (j, b) in rhs.limbs.iter().enumerate() {
+                let cur = res[i + j] as u128 + *a as u128 * *b as u128 + carry;
+                res[i + j] = cur as u64;
+                carry = cur >> 64;
+            }
+
+            res[i + rhs.limbs.len()] = carry as u64;
+        }
+
+        Big::from_limbs(&res)
+    }
+}
+
+impl fmt::Display for Big {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+
+        if self.is_zero() {
+            return write!(fmt, "0");
+        }
+
+        let mut n = self.clone();
+        let mut chunks = Vec::new();
+
+        while !n.is_zero() {
+            chunks.push(n.div_rem_small(CHUNK));
+        }
+
+        write!(fmt, "{}", chunks.pop().unwrap())?;
+
+        for chunk in chunks.iter().rev() {
+            write!(fmt, "{:019}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_big() {
+    let a = Big::from(u64::MAX);
+    let sq = &a * &a;
+
+    assert_eq!(u128::try_from(&sq), Ok(u64::MAX as u128 * u64::MAX as u128));
+    assert_eq!(sq.to_string(), (u64::MAX as u128 * u64::MAX as u128).to_string());
+    assert_eq!((&sq * &sq).to_string(), "115792089237316195398462578067141184799968521174335529155754622898352762650625");
+    assert_eq!(Big::default().to_string(), "0");
+    assert_eq!((&Big::default() * &a), Big::default());
+}
+
+#[test]
+fn test_add_rem() {
+    let a = Big::from(u64::MAX);
+    let sum = &a + &Big::from(1u64);
+
+    assert_eq!(sum, Big::from_limbs(&[0, 1]));
+    assert_eq!((&sum + &sum).to_string(), (2 * (u64::MAX as u128 + 1)).to_string());
+    assert_eq!(&Big::default() + &a, a);
+    assert_eq!(sum.rem(1_000_000_007), ((u64::MAX as u128 + 1) % 1_000_000_007) as u64);
+}
@@ -1,23 +1,68 @@
 use std::fs::read_to_string;
 
+pub mod big;
+
+#[cfg(feature = "rng")]
+pub mod rng;
+
 pub trait Parsable: Sized {
     fn parse(input: &str) -> nom::IResult<&str, Self>;
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineError {
+    pub line: usize,
+    pub content: String,
+}
+
+impl std::fmt::Display for LineError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "line {}: malformed input {:?}", self.line, self.content)
+    }
+}
+
+impl std::error::Error for LineError {}
+
 pub fn parse_file_vec<T: Parsable>(file: &str) -> Vec<T> {
-    let content = read_to_string(file).expect(&format!("reading {}", file));
+    let content = read_to_string(file).unwrap_or_else(|_| panic!("reading {}", file));
     let mut res = Vec::new();
 
     for line in content.lines() {
-        let (_, elem) = T::parse(&line).expect("unable to parse");
+        let (_, elem) = T::parse(line).expect("unable to parse");
         res.push(elem);
     }
 
     res
 }
 
+pub fn parse_lines<T: Parsable>(content: &str) -> Result<Vec<T>, Vec<LineError>> {
+    let mut res = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        match T::parse(line) {
+            Ok(("", elem)) => res.push(elem),
+            _ => errors.push(LineError {
+                line: i + 1,
+                content: line.to_string(),
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(res)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn try_parse_file_vec<T: Parsable>(file: &str) -> Result<Vec<T>, Vec<LineError>> {
+    let content = read_to_string(file).unwrap_or_else(|_| panic!("reading {}", file));
+    parse_lines(&content)
+}
+
 pub fn parse_file<T: Parsable>(file: &str) -> T {
-    let content = read_to_string(file).expect(&format!("reading {}", file));
+    let content = read_to_string(file).unwrap_or_else(|_| panic!("reading {}", file));
     T::parse(&content).expect("unable to parse").1
 }
 
@@ -29,6 +74,10 @@ pub fn input_vec<T: Parsable>() -> Vec<T> {
     parse_file_vec("input")
 }
 
+pub fn try_input_vec<T: Parsable>() -> Result<Vec<T>, Vec<LineError>> {
+    try_parse_file_vec("input")
+}
+
 pub fn test_input<T: Parsable>() -> T {
     parse_file("test.input")
 }
@@ -78,6 +127,28 @@ pub fn neigh(i: usize, cap: usize) -> Bound {
 mod test {
     use super::*;
 
+    #[derive(Debug, PartialEq)]
+    struct Num(u32);
+
+    impl Parsable for Num {
+        fn parse(input: &str) -> nom::IResult<&str, Num> {
+            let (input, n) = nom::character::complete::u32(input)?;
+            Ok((input, Num(n)))
+        }
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(parse_lines::<Num>("1\n2\n"), Ok(vec![Num(1), Num(2)]));
+        assert_eq!(
+            parse_lines::<Num>("1\nx\n3y\n"),
+            Err(vec![
+                LineError { line: 2, content: "x".to_string() },
+                LineError { line: 3, content: "3y".to_string() },
+            ])
+        );
+    }
+
     #[test]
     fn xyz() {
        assert_eq!(vec![0, 1], neigh(0, 10).collect::<Vec<_>>());
@@ -0,0 +1,35 @@
+// Xorshift, for reproducible test data. The same seed always gives the same
+// sequence, which is all the tests need from it.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    // xorshift never leaves a zero state, so zero picks another seed
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: if seed == 0 { 0x2545_f491_4f6c_dd1d } else { seed } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    // in 0..n, slightly biased towards small values for n that are not
+    // powers of two
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+#[test]
+fn test_rng() {
+    let mut a = Rng::new(7);
+    let mut b = Rng::new(7);
+
+    assert_eq!((0..10).map(|_| a.next_u64()).collect::<Vec<_>>(), (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+    assert!((0..1000).all(|_| a.below(6) < 6));
+    assert_ne!(Rng::new(0).next_u64(), 0);
+}